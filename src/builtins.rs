@@ -0,0 +1,363 @@
+use lazy_static::lazy_static;
+use num_bigint::BigInt;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use rayon::prelude::*;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::calc::{self, Settings, Value};
+use crate::error::{CalcError, Span};
+use crate::integer;
+use crate::matrix;
+use crate::stats;
+use crate::trig;
+use crate::number::{CalcNumber, NumberMode};
+
+// No upper limit for the variadic functions
+const ANY: usize = usize::MAX;
+
+// Longest list a `range` may build
+const MAX_RANGE_LEN: usize = 10_000_000;
+
+lazy_static! {
+    // The smallest and the largest number of arguments of the built-in functions
+    pub static ref BUILTINS: HashMap<&'static str, (usize, usize)> = {
+        let mut m = HashMap::new();
+        m.insert("abs", (1, 1));
+        m.insert("min", (1, ANY));
+        m.insert("max", (1, ANY));
+        m.insert("gcd", (2, ANY));
+        m.insert("lcm", (2, ANY));
+        m.insert("pow", (2, 2));
+        m.insert("sqrt", (1, 1));
+        m.insert("cbrt", (1, 1));
+        m.insert("log", (1, 2));
+        m.insert("log2", (1, 1));
+        m.insert("ln", (1, 1));
+        m.insert("exp", (1, 1));
+        m.insert("floor", (1, 1));
+        m.insert("ceil", (1, 1));
+        m.insert("round", (1, 1));
+        m.insert("sign", (1, 1));
+        m.insert("clamp", (3, 3));
+        m.insert("sin", (1, 1));
+        m.insert("cos", (1, 1));
+        m.insert("tan", (1, 1));
+        m.insert("asin", (1, 1));
+        m.insert("acos", (1, 1));
+        m.insert("atan", (1, 1));
+        m.insert("atan2", (2, 2));
+        m.insert("sinh", (1, 1));
+        m.insert("cosh", (1, 1));
+        m.insert("tanh", (1, 1));
+        m.insert("asinh", (1, 1));
+        m.insert("acosh", (1, 1));
+        m.insert("atanh", (1, 1));
+        m.insert("hypot", (2, ANY));
+        m.insert("nCr", (2, 2));
+        m.insert("nPr", (2, 2));
+        m.insert("is_prime", (1, 1));
+        m.insert("next_prime", (1, 1));
+        m.insert("factorize", (1, 1));
+        m.insert("totient", (1, 1));
+        m.insert("modinv", (2, 2));
+        m.insert("modpow", (3, 3));
+        m.insert("fib", (1, 1));
+        m.insert("isqrt", (1, 1));
+        m.insert("iroot", (2, 2));
+        m.insert("list", (0, ANY));
+        m.insert("range", (2, 3));
+        // `sum(v)` or `sum(i, 1..100, i**2)`, the second form is built into a lazy `Reduce` node
+        m.insert("sum", (1, 3));
+        m.insert("prod", (1, 3));
+        m.insert("len", (1, 1));
+        m.insert("at", (2, 2));
+        m.insert("slice", (2, 3));
+        m.insert("concat", (1, ANY));
+        m.insert("transpose", (1, 1));
+        m.insert("det", (1, 1));
+        m.insert("inv", (1, 1));
+        m.insert("identity", (1, 1));
+        m.insert("solve", (2, 2));
+        m.insert("map", (2, 2));
+        m.insert("filter", (2, 2));
+        m.insert("fold", (3, 3));
+        m.insert("sum_over", (2, 2));
+        m.insert("compose", (1, ANY));
+        m.insert("mean", (1, 1));
+        m.insert("median", (1, 1));
+        m.insert("mode", (1, 1));
+        m.insert("variance", (1, 1));
+        m.insert("variance_s", (1, 1));
+        m.insert("stddev", (1, 1));
+        m.insert("stddev_s", (1, 1));
+        m.insert("percentile", (2, 2));
+        m.insert("count", (1, 2));
+        m.insert("histogram", (2, 2));
+        m.insert("correlation", (2, 2));
+        // Built into a lazy `Condition` node, it is only called when it is used as a value
+        m.insert("if", (3, 3));
+        return m;
+    };
+}
+
+pub fn is_builtin(name: &str) -> bool {
+    return BUILTINS.contains_key(name);
+}
+
+// The parser builds these calls itself like `1..5` or `v[0]`, so a user definition cannot replace them
+pub fn is_reserved(name: &str) -> bool {
+    return matches!(name, "if" | "sum" | "prod" | "range" | "list" | "at" | "slice");
+}
+
+pub fn accepts(name: &str, argc: usize) -> bool {
+    let (min, max) = BUILTINS[name];
+    return min <= argc && argc <= max;
+}
+
+// A higher-order call is a level of the recursion too, its parallel iteration needs more stack than a plain call
+pub(crate) fn nested(depth: usize, char_idx: usize) -> Result<usize, CalcError> {
+    return depth.checked_sub(1).ok_or(CalcError::Recursion(Span::at(char_idx)));
+}
+
+// The number of arguments is already checked when the expression tree is built
+// `depth` limits the user-defined functions called by the higher-order ones
+pub fn call(name: &str, args: Vec<Value>, depth: usize, settings: &Settings, char_idx: usize) -> Result<Value, CalcError> {
+    match name {
+        "list" => Ok(Value::List(args)),
+        "len" => {
+            let len = args[0].list(char_idx)?.len();
+            Ok(Value::Number(CalcNumber::from_integer(BigInt::from(len), settings.mode, char_idx)?))
+        },
+        "at" => {
+            let items = args[0].list(char_idx)?;
+            match offset(&args[1].number(char_idx)?, items.len(), char_idx)? {
+                idx if idx >= 0 && idx < items.len() as i128 => Ok(items[idx as usize].clone()),
+                _ => Err(CalcError::Index(Span::at(char_idx)))
+            }
+        },
+        // The bounds of a slice are limited to the list like in `v[1:100]`
+        "slice" => {
+            let items = args[0].list(char_idx)?;
+            let len = items.len() as i128;
+            let start = offset(&args[1].number(char_idx)?, items.len(), char_idx)?.clamp(0, len);
+            let end = match args.get(2) {
+                Some(end) => offset(&end.number(char_idx)?, items.len(), char_idx)?.clamp(0, len),
+                None => len
+            };
+            Ok(Value::List(if start < end { items[start as usize..end as usize].to_vec() } else { Vec::new() }))
+        },
+        "sum" | "prod" => {
+            if args.len() != 1 { return Err(CalcError::Arity(Span::at(char_idx))); }
+            let identity = if name == "sum" { BigInt::zero() } else { BigInt::one() };
+            let mut res = CalcNumber::from_integer(identity, settings.mode, char_idx)?;
+            for item in args[0].list(char_idx)? {
+                let item = item.number(char_idx)?;
+                res = if name == "sum" { res.add(&item, char_idx)? } else { res.mul(&item, char_idx)? };
+            }
+            Ok(Value::Number(res))
+        },
+        "concat" => Ok(Value::List(args.iter().flat_map(|arg| arg.to_items()).collect())),
+        "transpose" => matrix::transpose(&args[0], char_idx),
+        "det" => matrix::det(&args[0], char_idx),
+        "inv" => matrix::inverse(&args[0], char_idx),
+        "identity" => matrix::identity(&args[0].number(char_idx)?, settings.mode, char_idx),
+        "solve" => matrix::solve(&args[0], &args[1], char_idx),
+        "map" => {
+            let depth = nested(depth, char_idx)?;
+            let items = args[1].list(char_idx)?;
+            let res = items.par_iter().map(|item| calc::apply(&args[0], vec![item.clone()], depth, settings, char_idx)).collect::<Result<Vec<Value>, CalcError>>()?;
+            Ok(Value::List(res))
+        },
+        "filter" => {
+            let depth = nested(depth, char_idx)?;
+            let items = args[1].list(char_idx)?;
+            let keep = items.par_iter().map(|item| calc::apply(&args[0], vec![item.clone()], depth, settings, char_idx).map(|res| res.to_bool())).collect::<Result<Vec<bool>, CalcError>>()?;
+            Ok(Value::List(items.iter().zip(keep).filter(|(_, keep)| *keep).map(|(item, _)| item.clone()).collect()))
+        },
+        "fold" => {
+            let depth = nested(depth, char_idx)?;
+            let mut res = args[1].clone();
+            for item in args[2].list(char_idx)? {
+                res = calc::apply(&args[0], vec![res, item.clone()], depth, settings, char_idx)?;
+            }
+            Ok(res)
+        },
+        "sum_over" => {
+            let depth = nested(depth, char_idx)?;
+            let items = args[0].list(char_idx)?;
+            let terms = items.par_iter().map(|item| calc::apply(&args[1], vec![item.clone()], depth, settings, char_idx)?.number(char_idx)).collect::<Result<Vec<CalcNumber>, CalcError>>()?;
+            let mut res = CalcNumber::from_integer(BigInt::zero(), settings.mode, char_idx)?;
+            for term in &terms {
+                res = res.add(term, char_idx)?;
+            }
+            Ok(Value::Number(res))
+        },
+        "mean" => stats::mean(&args[0], char_idx),
+        "median" => stats::median(&args[0], char_idx),
+        "mode" => stats::mode(&args[0], char_idx),
+        "variance" | "variance_s" => stats::variance(&args[0], name == "variance_s", char_idx),
+        "stddev" | "stddev_s" => stats::stddev(&args[0], name == "stddev_s", char_idx),
+        "percentile" => stats::percentile(&args[0], &args[1].number(char_idx)?, char_idx),
+        "histogram" => stats::histogram(&args[0], &args[1].number(char_idx)?, settings.mode, char_idx),
+        "correlation" => stats::correlation(&args[0], &args[1], char_idx),
+        // `count(v)`, `count(v, x)` counts the items equal to `x` and `count(v, f)` the items where `f` is true
+        "count" => match args.get(1) {
+            None => call("len", args, depth, settings, char_idx),
+            Some(func) if func.is_function() => {
+                let items = call("filter", vec![func.clone(), args[0].clone()], depth, settings, char_idx)?;
+                call("len", vec![items], depth, settings, char_idx)
+            },
+            Some(item) => stats::occurrences(&args[0], &item.number(char_idx)?, settings.mode, char_idx)
+        },
+        "compose" => {
+            if !args.iter().all(|arg| arg.is_function()) { return Err(CalcError::NotFunction(Span::at(char_idx))); }
+            Ok(calc::compose(args, char_idx))
+        },
+        // The lazy evaluation is lost when `if` is passed as a value
+        "if" => Ok(if args[0].to_bool() { args[1].clone() } else { args[2].clone() }),
+        // `min` and `max` take the items of the list arguments too, like `max([3, 9], 4)`
+        "min" | "max" => {
+            let items: Vec<Value> = args.iter().flat_map(|arg| arg.to_items()).collect();
+            if items.is_empty() { return Err(CalcError::Domain(Span::at(char_idx))); }
+            let nums = items.iter().map(|item| item.number(char_idx)).collect::<Result<Vec<CalcNumber>, CalcError>>()?;
+            call_numeric(name, &nums, char_idx).map(Value::Number)
+        },
+        _ => {
+            let nums = args.iter().map(|arg| arg.number(char_idx)).collect::<Result<Vec<CalcNumber>, CalcError>>()?;
+            if name == "range" { return range(&nums, char_idx); }
+            if matches!(name, "nCr" | "nPr" | "is_prime" | "next_prime" | "factorize" | "totient" | "modinv" | "modpow" | "fib" | "isqrt" | "iroot") {
+                return integer::call(name, &nums, char_idx);
+            }
+            if matches!(name, "sin" | "cos" | "tan" | "asin" | "acos" | "atan" | "atan2" | "sinh" | "cosh" | "tanh" | "asinh" | "acosh" | "atanh" | "hypot") {
+                return trig::call(name, &nums, settings.angle, char_idx).map(Value::Number);
+            }
+            call_numeric(name, &nums, char_idx).map(Value::Number)
+        }
+    }
+}
+
+fn call_numeric(name: &str, args: &[CalcNumber], char_idx: usize) -> Result<CalcNumber, CalcError> {
+    let mode = args[0].mode();
+    match name {
+        "abs" => args[0].abs(char_idx),
+        "min" => extremum(args, Ordering::Less, char_idx),
+        "max" => extremum(args, Ordering::Greater, char_idx),
+        "gcd" => {
+            let mut res = BigInt::zero();
+            for arg in args {
+                res = gcd(res, arg.to_big_integer(char_idx)?);
+            }
+            CalcNumber::from_integer(res, mode, char_idx)
+        },
+        "lcm" => {
+            let mut res = args[0].to_big_integer(char_idx)?.abs();
+            for arg in &args[1..] {
+                let value = arg.to_big_integer(char_idx)?.abs();
+                if res.is_zero() || value.is_zero() {
+                    res = BigInt::zero();
+                }else{
+                    res = &res / gcd(res.clone(), value.clone()) * value;
+                }
+            }
+            CalcNumber::from_integer(res, mode, char_idx)
+        },
+        "pow" => args[0].pow(&args[1], char_idx),
+        "sqrt" => args[0].sqrt(char_idx),
+        "cbrt" => args[0].cbrt(char_idx),
+        "log" => match args.get(1) {
+            Some(base) => args[0].log(base, char_idx),
+            None => args[0].log(&CalcNumber::from_integer(BigInt::from(10), mode, char_idx)?, char_idx)
+        },
+        "log2" => args[0].log(&CalcNumber::from_integer(BigInt::from(2), mode, char_idx)?, char_idx),
+        "ln" => args[0].ln(char_idx),
+        "exp" => args[0].exp(char_idx),
+        "floor" => Ok(args[0].floor()),
+        "ceil" => Ok(args[0].ceil()),
+        "round" => Ok(args[0].round()),
+        "sign" => args[0].signum(char_idx),
+        "clamp" => {
+            if args[1].compare(&args[2]) == Some(Ordering::Greater) { return Err(CalcError::Domain(Span::at(char_idx))); }
+            let res = extremum(&args[..2], Ordering::Greater, char_idx)?;
+            extremum(&[res, args[2].clone()], Ordering::Less, char_idx)
+        },
+        _ => unreachable!()
+    }
+}
+
+// The smallest or the largest argument, the numbers which cannot be ordered are rejected
+fn extremum(args: &[CalcNumber], wanted: Ordering, char_idx: usize) -> Result<CalcNumber, CalcError> {
+    let mut res = args[0].clone();
+    for arg in &args[1..] {
+        match arg.compare(&res) {
+            Some(ord) => if ord == wanted { res = arg.convert(res.mode()); },
+            None => { return Err(CalcError::Unsupported(Span::at(char_idx))); }
+        }
+    }
+    if res.compare(&res).is_none() { return Err(CalcError::Unsupported(Span::at(char_idx))); }
+    return Ok(res);
+}
+
+fn gcd(mut a: BigInt, mut b: BigInt) -> BigInt {
+    while !b.is_zero() {
+        let r = &a % &b;
+        a = b;
+        b = r;
+    }
+    return a.abs();
+}
+
+// Position of an item, a negative index counts from the end of the list
+fn offset(index: &CalcNumber, len: usize, char_idx: usize) -> Result<i128, CalcError> {
+    let index = match index.to_big_integer(char_idx)?.to_i128() {
+        Some(index) => index,
+        None => { return Err(CalcError::Index(Span::at(char_idx))); }
+    };
+    return Ok(if index < 0 { index + len as i128 } else { index });
+}
+
+// `range(start, end, step)` with an inclusive end, the step may be negative
+fn range(args: &[CalcNumber], char_idx: usize) -> Result<Value, CalcError> {
+    let seq = Sequence::new(args, char_idx)?;
+    if seq.len() > MAX_RANGE_LEN { return Err(CalcError::TooLarge(Span::at(char_idx))); }
+    let items = (0..seq.len()).into_par_iter().map(|k| seq.get(k, char_idx).map(Value::Number)).collect::<Result<Vec<Value>, CalcError>>()?;
+    return Ok(Value::List(items));
+}
+
+// The items of a range without building its list, they are computed as fractions so a float step does not drift
+pub(crate) struct Sequence {
+    start: CalcNumber,
+    step: CalcNumber,
+    len: usize,
+    mode: NumberMode,
+}
+
+impl Sequence {
+    // Start, end and the optional step of the range
+    pub(crate) fn new(args: &[CalcNumber], char_idx: usize) -> Result<Sequence, CalcError> {
+        if args.iter().any(|arg| arg.compare(arg).is_none()) { return Err(CalcError::Unsupported(Span::at(char_idx))); }
+        let exact = |arg: &CalcNumber| arg.convert(NumberMode::Rational);
+        let (start, end) = (exact(&args[0]), exact(&args[1]));
+        let step = match args.get(2) {
+            Some(step) => exact(step),
+            None => CalcNumber::from_integer(BigInt::one(), NumberMode::Rational, char_idx)?
+        };
+        if step.is_zero() { return Err(CalcError::Domain(Span::at(char_idx))); }
+        let count = end.sub(&start, char_idx)?.div(&step, char_idx)?.floor().to_big_integer(char_idx)? + BigInt::one();
+        let len = match count.to_usize() {
+            Some(len) => len,
+            None if count.is_positive() => { return Err(CalcError::TooLarge(Span::at(char_idx))); },
+            None => 0
+        };
+        return Ok(Sequence { start, step, len, mode: args[0].mode() });
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        return self.len;
+    }
+
+    pub(crate) fn get(&self, k: usize, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        let k = CalcNumber::from_integer(BigInt::from(k), NumberMode::Rational, char_idx)?;
+        return Ok(self.start.add(&self.step.mul(&k, char_idx)?, char_idx)?.convert(self.mode));
+    }
+}
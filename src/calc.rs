@@ -1,17 +1,23 @@
 use lazy_static::lazy_static;
+use rayon::prelude::*;
+use std::cmp::Ordering;
 use std::collections::{HashMap, VecDeque};
 use std::fmt::Debug;
 use std::fs::OpenOptions;
 use std::io::Write;
-use std::num::Wrapping;
-use std::str::Chars;
 use std::sync::Mutex;
 
-type ErrorMsg = (String, Option<usize>);
-type ErrorCode = (u32, Option<usize>);
-type CalcNumber = Wrapping<u32>;
+use num_bigint::BigInt;
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+use crate::builtins;
+use crate::constants;
+use crate::error::{CalcError, Span};
+use crate::matrix;
+use crate::suggest;
+use crate::number::{AngleMode, CalcNumber, NumberMode, OutputBase, Overflow, RationalDisplay};
+
+
+#[derive(Debug, PartialEq, Clone)]
 enum TOKEN {
     PLUS(usize),
     MINUS(usize),
@@ -21,6 +27,7 @@ enum TOKEN {
     FACT(usize),
     SQRT(usize),
     MOD(usize),
+    NEG(usize),
 
     EQUAL(usize),
     NOT(usize),
@@ -36,21 +43,53 @@ enum TOKEN {
     BAND(usize),
     BOR(usize),
     BXOR(usize),
+    BNOT(usize),
+    SHL(usize),
+    SHR(usize),
+    ROL(usize),
+    ROR(usize),
+    CONCAT(usize),
+    MATMUL(usize),
+    // `1..10` and `1..10 step 2`
+    RANGE(usize),
+    STEP(usize),
 
     EXPR(Box<ASTNode>, usize),
 
     LPAREN(usize),
     RPAREN(usize),
+    COMMA(usize),
     NUMBER(CalcNumber, usize),
+    // name, number of arguments
+    FUNC(String, usize, usize),
+    // `v[i]` or `v[a:b]`, a call of `at` or `slice` whose first argument is the operand before it
+    INDEX(String, usize, usize),
+    PARAM(String, usize),
+    QUESTION(usize),
+    COLON(usize),
+    // A `?` whose `:` is already found, it takes the condition and both branches
+    TERNARY(usize),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq)]
 enum ASTNode {
     Number(CalcNumber),
     Expression(Box<ASTNode>),
     ULOperator { op: TOKEN, left: Box<ASTNode>},
     UROperator { op: TOKEN, right: Box<ASTNode>},
-    BOperator { op: TOKEN, left: Box<ASTNode>, right: Box<ASTNode>}
+    BOperator { op: TOKEN, left: Box<ASTNode>, right: Box<ASTNode>},
+    Function { op: TOKEN, args: Vec<ASTNode> },
+    // A parameter of a function, replaced by the argument before evaluation
+    Param(String),
+    Lambda(Lambda),
+    // An already evaluated value like a function passed as an argument
+    Value(Value),
+    // A call of a function value, `f(x)` where `f` is a parameter
+    Apply { op: TOKEN, callee: Box<ASTNode>, args: Vec<ASTNode> },
+    // `cond ? then : otherwise` and `if(cond, then, otherwise)`
+    Condition { cond: Box<ASTNode>, then: Box<ASTNode>, otherwise: Box<ASTNode> },
+    // `sum(var, over, body)` and `prod(var, over, body)`, the body is evaluated for every item
+    Reduce { op: TOKEN, var: String, over: Box<ASTNode>, body: Box<ASTNode> },
 }
 
 #[derive(PartialEq)]
@@ -60,16 +99,35 @@ enum Associativity {
     NOT,
 }
 
+impl TOKEN {
+    fn char_idx(&self) -> usize {
+        match self {
+            TOKEN::PLUS(char_idx) | TOKEN::MINUS(char_idx) | TOKEN::MUL(char_idx) | TOKEN::DIV(char_idx) |
+            TOKEN::POW(char_idx) | TOKEN::FACT(char_idx) | TOKEN::SQRT(char_idx) | TOKEN::MOD(char_idx) |
+            TOKEN::NEG(char_idx) | TOKEN::EQUAL(char_idx) | TOKEN::NOT(char_idx) | TOKEN::GT(char_idx) |
+            TOKEN::GE(char_idx) | TOKEN::LT(char_idx) | TOKEN::LE(char_idx) | TOKEN::NEQUAL(char_idx) |
+            TOKEN::AND(char_idx) | TOKEN::OR(char_idx) | TOKEN::XOR(char_idx) | TOKEN::BAND(char_idx) |
+            TOKEN::BOR(char_idx) | TOKEN::BXOR(char_idx) | TOKEN::BNOT(char_idx) | TOKEN::SHL(char_idx) |
+            TOKEN::SHR(char_idx) | TOKEN::ROL(char_idx) | TOKEN::ROR(char_idx) | TOKEN::CONCAT(char_idx) |
+            TOKEN::MATMUL(char_idx) | TOKEN::RANGE(char_idx) | TOKEN::STEP(char_idx) | TOKEN::EXPR(_, char_idx) | TOKEN::INDEX(_, _, char_idx) |
+            TOKEN::LPAREN(char_idx) | TOKEN::RPAREN(char_idx) | TOKEN::COMMA(char_idx) |
+            TOKEN::NUMBER(_, char_idx) | TOKEN::FUNC(_, _, char_idx) | TOKEN::PARAM(_, char_idx) |
+            TOKEN::QUESTION(char_idx) | TOKEN::COLON(char_idx) | TOKEN::TERNARY(char_idx) => *char_idx
+        }
+    }
+}
+
 impl ASTNode {
-    fn eval(&self) -> Result<CalcNumber, ErrorCode> {
+    // `depth` is how many more user-defined function calls may be nested
+    fn eval(&self, depth: usize, settings: &Settings) -> Result<Value, CalcError> {
         match self {
-            ASTNode::Number(val) => Ok(*val),
-            ASTNode::Expression(sub_root) => match sub_root.eval() {
+            ASTNode::Number(val) => Ok(Value::Number(val.clone())),
+            ASTNode::Expression(sub_root) => match sub_root.eval(depth, settings) {
                 Ok(sub_res) => Ok(sub_res),
                 Err(err_code) => { return Err(err_code); }
             },
             ASTNode::BOperator { op, left, right } => {
-                let (maybe_left_val, maybe_right_val): (Result<CalcNumber, ErrorCode>, Result<CalcNumber, ErrorCode>) = rayon::join(|| left.eval(), || right.eval());
+                let (maybe_left_val, maybe_right_val): (Result<Value, CalcError>, Result<Value, CalcError>) = rayon::join(|| left.eval(depth, settings), || right.eval(depth, settings));
                 let lval = match maybe_left_val {
                     Err(err_code) => { return Err(err_code); },
                     Ok(v) => v
@@ -78,59 +136,271 @@ impl ASTNode {
                     Err(err_code) => { return Err(err_code); },
                     Ok(v) => v
                 };
-                match op {
-                    TOKEN::PLUS(_) => Ok(lval + rval),
-                    TOKEN::MINUS(_) => Ok(lval - rval),
-                    TOKEN::MUL(_) => Ok(lval * rval),
-                    TOKEN::DIV(char_idx) => {
-                        if rval == Wrapping(0) { return Err((DIVIDE_BY_ZERO_ERROR, Some(*char_idx))); }
-                        return Ok(lval / rval);
-                    },
-                    TOKEN::POW(_) => Ok(Wrapping(lval.0.wrapping_pow(rval.0))),
-                    TOKEN::MOD(char_idx) => {
-                        if rval.0 == 0 { return Err((DIVIDE_BY_ZERO_ERROR, Some(*char_idx))); }
-                        return Ok(Wrapping(lval.0%rval.0));
-                    },
-                    TOKEN::AND(_) => Ok(to_calc_num(to_bool(lval) && to_bool(rval))),
-                    TOKEN::OR(_) => Ok(to_calc_num(to_bool(lval) || to_bool(rval))),
-                    TOKEN::XOR(_) => Ok(to_calc_num(to_bool(lval)^to_bool(rval))),
-                    TOKEN::BOR(_) => Ok(lval|rval),
-                    TOKEN::BAND(_) => Ok(lval&rval),
-                    TOKEN::BXOR(_) => Ok(lval^rval),
-                    TOKEN::EQUAL(_) => Ok(to_calc_num(lval==rval)),
-                    TOKEN::GT(_) => Ok(to_calc_num(lval>rval)),
-                    TOKEN::GE(_) => Ok(to_calc_num(lval>=rval)),
-                    TOKEN::LT(_) => Ok(to_calc_num(lval<rval)),
-                    TOKEN::LE(_) => Ok(to_calc_num(lval<=rval)),
-                    TOKEN::NEQUAL(_) => Ok(to_calc_num(lval!=rval)),
-                    _ => unreachable!(),
-                }
+                apply_binary(op, &lval, &rval)
             },
             ASTNode::ULOperator { op, left } => {
-                let meybe_left: Result<CalcNumber, ErrorCode> = left.eval();
+                let meybe_left: Result<Value, CalcError> = left.eval(depth, settings);
                 let lval = match meybe_left {
                     Err(err_code) => { return Err(err_code); },
                     Ok(v) => v
                 };
-                match op {
-                    TOKEN::FACT(_) => Ok(factorial(lval)),
-                    _ => unreachable!(),
-                }
+                apply_unary(op, &lval)
             },
             ASTNode::UROperator { op, right } => {
-                let maybe_right_val:Result<CalcNumber, ErrorCode> = right.eval();
+                let maybe_right_val:Result<Value, CalcError> = right.eval(depth, settings);
                 let rval = match maybe_right_val {
                     Err(err_code) => { return Err(err_code); },
                     Ok(v) => v
                 };
+                apply_unary(op, &rval)
+            },
+            ASTNode::Function { op, args } => {
+                let vals = match args.par_iter().map(|arg| arg.eval(depth, settings)).collect::<Result<Vec<Value>, CalcError>>() {
+                    Err(err_code) => { return Err(err_code); },
+                    Ok(v) => v
+                };
                 match op {
-                    TOKEN::SQRT(_) => Ok(Wrapping(f64::from(rval.0).sqrt() as u32)),
-                    TOKEN::NOT(_) => Ok(to_calc_num(!to_bool(rval))),
+                    TOKEN::FUNC(name, _, char_idx) => {
+                        if builtins::is_builtin(name) && !is_user_function(name, "") { return builtins::call(name, vals, depth, settings, *char_idx); }
+                        match VARS::get_func(name) {
+                            Some(func) => apply(&func, vals, depth, settings, *char_idx),
+                            None => Err(CalcError::UnknownFunction(name.clone(), Span::at(*char_idx), suggestion(name, &[], true)))
+                        }
+                    },
                     _ => unreachable!()
                 }
-            }
+            },
+            ASTNode::Apply { op, callee, args } => {
+                let func = callee.eval(depth, settings)?;
+                let vals = match args.par_iter().map(|arg| arg.eval(depth, settings)).collect::<Result<Vec<Value>, CalcError>>() {
+                    Err(err_code) => { return Err(err_code); },
+                    Ok(v) => v
+                };
+                apply(&func, vals, depth, settings, op.char_idx())
+            },
+            // Only the taken branch is evaluated, so a recursion can stop
+            ASTNode::Condition { cond, then, otherwise } => {
+                let cval = match cond.eval(depth, settings) {
+                    Err(err_code) => { return Err(err_code); },
+                    Ok(v) => v
+                };
+                if cval.to_bool() { then.eval(depth, settings) } else { otherwise.eval(depth, settings) }
+            },
+            ASTNode::Reduce { op, var, over, body } => reduce(op, var, over, body, depth, settings),
+            ASTNode::Lambda(lambda) => Ok(Value::Function(lambda.clone())),
+            ASTNode::Value(val) => Ok(val.clone()),
+            // The lexer only makes parameters of names which are bound before the evaluation
+            ASTNode::Param(_) => unreachable!(),
         }
     }
+
+    // Puts the arguments in place of the parameters, the constants of the body follow the mode of the arguments
+    fn bind(&self, params: &[String], args: &[Value]) -> ASTNode {
+        let mode = match args.first() {
+            Some(Value::Number(arg)) => Some(arg.mode()),
+            _ => None
+        };
+        return self.substitute(params, args, mode);
+    }
+
+    fn substitute(&self, params: &[String], args: &[Value], mode: Option<NumberMode>) -> ASTNode {
+        let sub = |node: &ASTNode| Box::new(node.substitute(params, args, mode));
+        match self {
+            ASTNode::Number(val) => ASTNode::Number(match mode {
+                Some(mode) => val.convert(mode),
+                None => val.clone()
+            }),
+            ASTNode::Param(name) => match params.iter().position(|p| p == name) {
+                Some(idx) => args[idx].to_node(),
+                None => self.clone()
+            },
+            ASTNode::Expression(sub_root) => ASTNode::Expression(sub(sub_root)),
+            ASTNode::ULOperator { op, left } => ASTNode::ULOperator { op: op.clone(), left: sub(left) },
+            ASTNode::UROperator { op, right } => ASTNode::UROperator { op: op.clone(), right: sub(right) },
+            ASTNode::BOperator { op, left, right } => ASTNode::BOperator { op: op.clone(), left: sub(left), right: sub(right) },
+            ASTNode::Function { op, args: call_args } => {
+                let call_args = call_args.iter().map(|arg| arg.substitute(params, args, mode)).collect();
+                match op {
+                    // Calling a parameter calls the function value given as the argument
+                    TOKEN::FUNC(name, _, _) if params.contains(name) => ASTNode::Apply { op: op.clone(), callee: sub(&ASTNode::Param(name.clone())), args: call_args },
+                    _ => ASTNode::Function { op: op.clone(), args: call_args }
+                }
+            },
+            ASTNode::Apply { op, callee, args: call_args } => ASTNode::Apply { op: op.clone(), callee: sub(callee), args: call_args.iter().map(|arg| arg.substitute(params, args, mode)).collect() },
+            ASTNode::Condition { cond, then, otherwise } => ASTNode::Condition { cond: sub(cond), then: sub(then), otherwise: sub(otherwise) },
+            ASTNode::Reduce { op, var, over, body } => {
+                let (outer_params, outer_args) = hide(params, args, std::slice::from_ref(var));
+                ASTNode::Reduce { op: op.clone(), var: var.clone(), over: sub(over), body: Box::new(body.substitute(&outer_params, &outer_args, mode)) }
+            },
+            // The own parameters of an inner function hide the outer ones
+            ASTNode::Lambda(lambda) => {
+                let (outer_params, outer_args) = hide(params, args, &lambda.params);
+                ASTNode::Lambda(Lambda { body: Box::new(lambda.body.substitute(&outer_params, &outer_args, mode)), ..lambda.clone() })
+            },
+            ASTNode::Value(_) => self.clone(),
+        }
+    }
+}
+
+// The bound parameters without the ones named in `hidden`
+fn hide(params: &[String], args: &[Value], hidden: &[String]) -> (Vec<String>, Vec<Value>) {
+    return params.iter().zip(args)
+        .filter(|(param, _)| !hidden.contains(param))
+        .map(|(param, arg)| (param.clone(), arg.clone()))
+        .unzip();
+}
+
+// Number of items a thread of a reduction takes at once
+const REDUCE_CHUNK: usize = 1024;
+
+// Most items a `sum(i, a..b, ...)` may go over, it is larger than the longest `range` list as the items are never stored
+const MAX_REDUCE_LEN: usize = 100_000_000;
+
+// A range is not built as a list, its items are computed in chunks on the threads
+// the chunks are fixed so the float results are the same in every run
+fn reduce(op: &TOKEN, var: &str, over: &ASTNode, body: &ASTNode, depth: usize, settings: &Settings) -> Result<Value, CalcError> {
+    let (name, char_idx) = match op {
+        TOKEN::FUNC(name, _, char_idx) => (name.as_str(), *char_idx),
+        _ => unreachable!()
+    };
+    let depth = builtins::nested(depth, char_idx)?;
+    let (list, seq) = match over {
+        ASTNode::Function { op: TOKEN::FUNC(func, _, range_idx), args } if func == "range" => {
+            let bounds = args.iter().map(|arg| arg.eval(depth, settings)?.number(*range_idx)).collect::<Result<Vec<CalcNumber>, CalcError>>()?;
+            let seq = builtins::Sequence::new(&bounds, *range_idx)?;
+            if seq.len() > MAX_REDUCE_LEN { return Err(CalcError::TooLarge(Span::at(*range_idx))); }
+            (Vec::new(), Some(seq))
+        },
+        _ => (over.eval(depth, settings)?.list(char_idx)?.to_vec(), None)
+    };
+    let len = seq.as_ref().map_or(list.len(), |seq| seq.len());
+    let (combine, identity) = match name {
+        "sum" => (TOKEN::PLUS(char_idx), 0),
+        _ => (TOKEN::MUL(char_idx), 1)
+    };
+    let identity = Value::Number(CalcNumber::from_integer(BigInt::from(identity), settings.mode, char_idx)?);
+    let params = [var.to_string()];
+    let partials = (0..len.div_ceil(REDUCE_CHUNK)).into_par_iter().map(|chunk| {
+        let mut res = identity.clone();
+        for k in chunk*REDUCE_CHUNK..len.min((chunk+1)*REDUCE_CHUNK) {
+            let item = match &seq {
+                Some(seq) => Value::Number(seq.get(k, char_idx)?),
+                None => list.get(k).unwrap().clone()
+            };
+            res = apply_binary(&combine, &res, &body.bind(&params, &[item]).eval(depth, settings)?)?;
+        }
+        Ok(res)
+    }).collect::<Result<Vec<Value>, CalcError>>()?;
+    let mut res = identity;
+    for partial in partials {
+        res = apply_binary(&combine, &res, &partial)?;
+    }
+    return Ok(res);
+}
+
+// A list operand applies the operator to every item, two lists are paired item by item
+fn apply_binary(op: &TOKEN, lval: &Value, rval: &Value) -> Result<Value, CalcError> {
+    let items = match (lval, rval) {
+        (_, _) if matches!(op, TOKEN::CONCAT(_)) => {
+            let mut items = lval.to_items();
+            items.extend(rval.to_items());
+            return Ok(Value::List(items));
+        },
+        (_, _) if matches!(op, TOKEN::MATMUL(_)) => { return matrix::matmul(lval, rval, op.char_idx()); },
+        (Value::List(left), Value::List(right)) => {
+            if left.len() != right.len() { return Err(CalcError::Length(Span::at(op.char_idx()))); }
+            left.par_iter().zip(right).map(|(l, r)| apply_binary(op, l, r)).collect::<Result<Vec<Value>, CalcError>>()
+        },
+        (Value::List(left), _) => left.par_iter().map(|l| apply_binary(op, l, rval)).collect::<Result<Vec<Value>, CalcError>>(),
+        (_, Value::List(right)) => right.par_iter().map(|r| apply_binary(op, lval, r)).collect::<Result<Vec<Value>, CalcError>>(),
+        _ => {
+            let (lval, rval) = promote(lval.number(op.char_idx())?, rval.number(op.char_idx())?);
+            let mode = lval.mode();
+            let res = match op {
+                TOKEN::PLUS(char_idx) => lval.add(&rval, *char_idx),
+                TOKEN::MINUS(char_idx) => lval.sub(&rval, *char_idx),
+                TOKEN::MUL(char_idx) => lval.mul(&rval, *char_idx),
+                TOKEN::DIV(char_idx) => lval.div(&rval, *char_idx),
+                TOKEN::POW(char_idx) => lval.pow(&rval, *char_idx),
+                TOKEN::MOD(char_idx) => lval.rem(&rval, *char_idx),
+                TOKEN::AND(_) => Ok(CalcNumber::from_bool(lval.to_bool() && rval.to_bool(), mode)),
+                TOKEN::OR(_) => Ok(CalcNumber::from_bool(lval.to_bool() || rval.to_bool(), mode)),
+                TOKEN::XOR(_) => Ok(CalcNumber::from_bool(lval.to_bool() ^ rval.to_bool(), mode)),
+                TOKEN::BOR(char_idx) => lval.bit_or(&rval, *char_idx),
+                TOKEN::BAND(char_idx) => lval.bit_and(&rval, *char_idx),
+                TOKEN::BXOR(char_idx) => lval.bit_xor(&rval, *char_idx),
+                TOKEN::SHL(char_idx) => lval.shl(&rval, *char_idx),
+                TOKEN::SHR(char_idx) => lval.shr(&rval, *char_idx),
+                TOKEN::ROL(char_idx) => lval.rotl(&rval, *char_idx),
+                TOKEN::ROR(char_idx) => lval.rotr(&rval, *char_idx),
+                TOKEN::EQUAL(_) => Ok(CalcNumber::from_bool(lval.compare(&rval) == Some(Ordering::Equal), mode)),
+                TOKEN::GT(_) => Ok(CalcNumber::from_bool(lval.compare(&rval) == Some(Ordering::Greater), mode)),
+                TOKEN::GE(_) => Ok(CalcNumber::from_bool(lval.compare(&rval).is_some_and(|o| o.is_ge()), mode)),
+                TOKEN::LT(_) => Ok(CalcNumber::from_bool(lval.compare(&rval) == Some(Ordering::Less), mode)),
+                TOKEN::LE(_) => Ok(CalcNumber::from_bool(lval.compare(&rval).is_some_and(|o| o.is_le()), mode)),
+                TOKEN::NEQUAL(_) => Ok(CalcNumber::from_bool(lval.compare(&rval) != Some(Ordering::Equal), mode)),
+                _ => unreachable!(),
+            };
+            return res.map(Value::Number);
+        }
+    };
+    return items.map(Value::List);
+}
+
+// A whole operand takes the mode of a fractional one, so `2 * sin(30)` is not truncated in the integer modes
+fn promote(lval: CalcNumber, rval: CalcNumber) -> (CalcNumber, CalcNumber) {
+    if lval.mode().is_whole() && !rval.mode().is_whole() {
+        return (lval.convert(rval.mode()), rval);
+    }
+    return (lval, rval);
+}
+
+fn apply_unary(op: &TOKEN, val: &Value) -> Result<Value, CalcError> {
+    if let Value::List(items) = val {
+        return items.par_iter().map(|item| apply_unary(op, item)).collect::<Result<Vec<Value>, CalcError>>().map(Value::List);
+    }
+    let val = val.number(op.char_idx())?;
+    let res = match op {
+        TOKEN::FACT(char_idx) => val.factorial(*char_idx),
+        TOKEN::SQRT(char_idx) => val.sqrt(*char_idx),
+        TOKEN::NOT(_) => Ok(CalcNumber::from_bool(!val.to_bool(), val.mode())),
+        TOKEN::NEG(char_idx) => val.neg(*char_idx),
+        TOKEN::BNOT(char_idx) => val.bit_not(*char_idx),
+        _ => unreachable!()
+    };
+    return res.map(Value::Number);
+}
+
+// Calls a function value, the errors inside of it are reported at the call
+pub(crate) fn apply(func: &Value, args: Vec<Value>, depth: usize, settings: &Settings, char_idx: usize) -> Result<Value, CalcError> {
+    match func {
+        Value::Builtin(name) => {
+            if !builtins::accepts(name, args.len()) { return Err(CalcError::Arity(Span::at(char_idx))); }
+            builtins::call(name, args, depth, settings, char_idx)
+        },
+        Value::Function(lambda) => {
+            if lambda.params.len() != args.len() { return Err(CalcError::Arity(Span::at(char_idx))); }
+            if depth == 0 { return Err(CalcError::Recursion(Span::at(char_idx))); }
+            // The indices inside the body belong to the line of the definition
+            lambda.body.bind(&lambda.params, &args).eval(depth - 1, settings).map_err(|err| err.with_span(Span::at(char_idx)))
+        },
+        _ => Err(CalcError::NotFunction(Span::at(char_idx)))
+    }
+}
+
+// `h = compose(f, g)` gives `h(x)` as `f(g(x))`, it takes the parameters of the innermost function
+pub(crate) fn compose(funcs: Vec<Value>, char_idx: usize) -> Value {
+    let params = match funcs.last() {
+        Some(Value::Function(lambda)) => lambda.params.clone(),
+        _ => vec!["x".to_string()]
+    };
+    let text = format!("compose({})", funcs.iter().map(|func| func.to_string()).collect::<Vec<String>>().join(", "));
+    let op = TOKEN::FUNC("compose".to_string(), funcs.len(), char_idx);
+    let mut body: Vec<ASTNode> = params.iter().map(|param| ASTNode::Param(param.clone())).collect();
+    for func in funcs.into_iter().rev() {
+        body = vec![ASTNode::Apply { op: op.clone(), callee: Box::new(ASTNode::Value(func)), args: body }];
+    }
+    return Value::Function(Lambda { params, body: Box::new(body.pop().unwrap()), text });
 }
 
 lazy_static! {
@@ -138,9 +408,9 @@ lazy_static! {
 }
 
 impl LOG {
-    fn add(s: &String) {
+    fn add(s: &str) {
         let mut log = LOG.lock().unwrap();
-        log.push(s.clone());
+        log.push(s.to_string());
     }
     
     fn get() -> Vec<String> {
@@ -150,37 +420,75 @@ impl LOG {
 }
 
 
+// Store Expression Tree, Expression in String, Result
+// result is an option because a function is stored as a `Lambda` tree without a result
+type VarEntry = (ASTNode, String, Option<Value>);
+
 lazy_static! {
-    // Store Expression Tree, Expression in String, Result
-    // result is an option because the feature function may return not a result but a function
-    static ref VARS: Mutex<HashMap<String, (ASTNode, String, Option<Wrapping<u32>>)>> = Mutex::new(HashMap::new());
+    static ref VARS: Mutex<HashMap<String, VarEntry>> = Mutex::new(HashMap::new());
 }
 
 pub fn get_vars() -> Vec<String> {
     let mut lines:Vec<String> = Vec::new();
-    for (key, (_, expr, result)) in VARS.lock().unwrap().clone().into_iter(){
-        lines.push(String::from(format!("{}: {} => {}", key, expr, match result {
-            Some(v) => v.to_string(),
-            None => "Function".to_string()
-        })))
+    for (key, (root, expr, result)) in VARS.lock().unwrap().clone().into_iter(){
+        lines.push(match result {
+            Some(v) => format!("{}: {} => {}", key, expr, v),
+            None => format!("{}: {} => Function", signature(&key, &root), expr)
+        })
+    }
+    return lines;
+}
+
+pub fn get_funcs() -> Vec<String> {
+    let mut lines:Vec<String> = Vec::new();
+    for (key, (root, expr, result)) in VARS.lock().unwrap().clone().into_iter(){
+        match result {
+            None => lines.push(format!("{} = {}", signature(&key, &root), expr)),
+            Some(Value::Function(lambda)) => lines.push(format!("{}({}) = {}", key, lambda.params.join(", "), expr)),
+            Some(Value::Builtin(_)) => lines.push(format!("{} = {}", key, expr)),
+            Some(_) => {}
+        }
     }
     return lines;
 }
 
+// `f(x, y)` like name of a stored function
+fn signature(name: &str, root: &ASTNode) -> String {
+    return match root {
+        ASTNode::Lambda(lambda) => format!("{}({})", name, lambda.params.join(", ")),
+        _ => name.to_string()
+    };
+}
+
 impl VARS {
-    fn add(v_name: &String, expr_root: ASTNode, expr: &String, result: Option<Wrapping<u32>>) {
+    fn add(v_name: &str, expr_root: ASTNode, expr: &str, result: Option<Value>) {
         let mut vars = VARS.lock().unwrap();
-        vars.insert(v_name.clone(), (expr_root, expr.clone(), result));
+        vars.insert(v_name.to_string(), (expr_root, expr.to_string(), result));
     }
     
-    fn get_expr(v_name: &String) -> Option<ASTNode> {
-        return match VARS.lock().unwrap().get(v_name){
-            Some(v) => Some(v.0.clone()),
-            None => None
+    fn get_expr(v_name: &str) -> Option<ASTNode> {
+        return VARS.lock().unwrap().get(v_name).map(|v| v.0.clone());
+    }
+
+    // A user-defined function or a variable holding a function value
+    fn get_func(f_name: &str) -> Option<Value> {
+        return match VARS.lock().unwrap().get(f_name) {
+            Some((ASTNode::Lambda(lambda), _, None)) => Some(Value::Function(lambda.clone())),
+            Some((_, _, Some(val))) if val.is_function() => Some(val.clone()),
+            _ => None
         };
     }
 
-    fn get_result(v_name: &String) -> Option<Wrapping<u32>> {
+    // The stored names, only the ones which can be called when `functions` is set
+    fn names(functions: bool) -> Vec<String> {
+        let vars = VARS.lock().unwrap().clone();
+        return vars.into_iter()
+            .filter(|(_, (root, _, result))| !functions || matches!(root, ASTNode::Lambda(_)) || result.as_ref().is_some_and(|val| val.is_function()))
+            .map(|(key, _)| key)
+            .collect();
+    }
+
+    fn get_result(v_name: &str) -> Option<Value> {
         return match VARS.lock().unwrap().get(v_name){
             Some(v) => v.2.clone(),
             None => None
@@ -188,22 +496,226 @@ impl VARS {
     }
 }
 
-const UNKNOWN_TOKEN_ERROR: u32 = 1;
-const WRON_PAREN_ERROR: u32 = 2;
-const DIVIDE_BY_ZERO_ERROR: u32 = 3;
-const NO_RESULT_ERROR:u32 = 4;
-const ARG_MISS_ERROR:u32 = 9;
+// A function value like `x -> x*2`, `text` is how it is shown
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lambda {
+    params: Vec<String>,
+    body: Box<ASTNode>,
+    text: String,
+}
+
+// What a line evaluates to
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(CalcNumber),
+    List(Vec<Value>),
+    Function(Lambda),
+    // A built-in function used as a value like `map(abs, v)`
+    Builtin(String),
+}
+
+impl Value {
+    // The operators only take numbers
+    pub(crate) fn number(&self, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        return match self {
+            Value::Number(v) => Ok(v.clone()),
+            _ => Err(CalcError::NotNumber(Span::at(char_idx)))
+        };
+    }
+
+    pub(crate) fn list(&self, char_idx: usize) -> Result<&[Value], CalcError> {
+        return match self {
+            Value::List(items) => Ok(items),
+            _ => Err(CalcError::NotList(Span::at(char_idx)))
+        };
+    }
+
+    // The items of a list, any other value is a list of itself
+    pub(crate) fn to_items(&self) -> Vec<Value> {
+        return match self {
+            Value::List(items) => items.clone(),
+            _ => vec![self.clone()]
+        };
+    }
+
+    pub(crate) fn is_function(&self) -> bool {
+        return matches!(self, Value::Function(_) | Value::Builtin(_));
+    }
+
+    // Functions are true, lists are true when they are not empty
+    pub(crate) fn to_bool(&self) -> bool {
+        return match self {
+            Value::Number(v) => v.to_bool(),
+            Value::List(items) => !items.is_empty(),
+            Value::Function(_) | Value::Builtin(_) => true,
+        };
+    }
+
+    fn convert(&self, mode: NumberMode) -> Value {
+        return match self {
+            // A fractional result like `sin(30)` is not truncated in the integer modes
+            Value::Number(v) if mode.is_whole() && !v.mode().is_whole() => self.clone(),
+            Value::Number(v) => Value::Number(v.convert(mode)),
+            Value::List(items) => Value::List(items.iter().map(|item| item.convert(mode)).collect()),
+            _ => self.clone()
+        };
+    }
+
+    fn to_node(&self) -> ASTNode {
+        return match self {
+            Value::Number(v) => ASTNode::Number(v.clone()),
+            _ => ASTNode::Value(self.clone())
+        };
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Value::Number(v) => write!(f, "{}", v),
+            Value::List(items) => write!(f, "[{}]", items.iter().map(|item| item.to_string()).collect::<Vec<String>>().join(", ")),
+            Value::Function(lambda) => write!(f, "{}", lambda.text),
+            Value::Builtin(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Settings {
+    pub mode: NumberMode,
+    pub display: RationalDisplay,
+    pub base: OutputBase,
+    // How deep the user-defined functions may call each other
+    pub max_depth: usize,
+    pub angle: AngleMode,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        return Settings { mode: NumberMode::default(), display: RationalDisplay::default(), base: OutputBase::default(), max_depth: 1000, angle: AngleMode::default() };
+    }
+}
 
 lazy_static! {
-    static ref ERROR_MAP: HashMap<u32, &'static str> = {
-        let mut m = HashMap::new();
-        m.insert(UNKNOWN_TOKEN_ERROR, "Token cannot be parsed");
-        m.insert(WRON_PAREN_ERROR, "Wrong parenthesis found");
-        m.insert(DIVIDE_BY_ZERO_ERROR, "Divided by zero");
-        m.insert(NO_RESULT_ERROR, "No Result");
-
-        m.insert(ARG_MISS_ERROR, "Argumentum is missing");
-        return m;
+    static ref SETTINGS: Mutex<Settings> = Mutex::new(Settings::default());
+}
+
+impl SETTINGS {
+    fn get() -> Settings {
+        return *SETTINGS.lock().unwrap();
+    }
+
+    fn set(settings: Settings) {
+        *SETTINGS.lock().unwrap() = settings;
+    }
+}
+
+// Deeper recursions would overflow the stack of the evaluating threads
+const MAX_DEPTH_LIMIT: usize = 10000;
+
+// Applies a REPL setting like `:mode i16` and returns a short confirmation
+pub fn set_option(name: &str, value: &str) -> Result<String, String> {
+    let mut settings = SETTINGS::get();
+    match name {
+        "mode" => {
+            let mut mode = match NumberMode::from_name(value) {
+                Some(mode) => mode,
+                None => { return Err(format!("{}: Unknown number mode", value)); }
+            };
+            // Switching between integer widths keeps the overflow policy
+            if let (NumberMode::Int(new_ty), NumberMode::Int(old_ty)) = (&mut mode, settings.mode) {
+                new_ty.overflow = old_ty.overflow;
+            }
+            settings.mode = mode;
+        },
+        "overflow" => {
+            let overflow = match Overflow::from_name(value) {
+                Some(overflow) => overflow,
+                None => { return Err(format!("{}: Unknown overflow policy", value)); }
+            };
+            match &mut settings.mode {
+                NumberMode::Int(ty) => ty.overflow = overflow,
+                _ => { return Err(format!("{}: Overflow policy only applies to fixed width integers", settings.mode.name())); }
+            }
+        },
+        "display" => {
+            settings.display = match RationalDisplay::from_name(value) {
+                Some(display) => display,
+                None => { return Err(format!("{}: Unknown display format", value)); }
+            };
+        },
+        "base" => {
+            settings.base = match OutputBase::from_name(value) {
+                Some(base) => base,
+                None => { return Err(format!("{}: Unknown base, use dec, hex, oct, bin, all or 2..36", value)); }
+            };
+        },
+        "depth" => {
+            settings.max_depth = match value.parse() {
+                Ok(depth) if depth > 0 && depth <= MAX_DEPTH_LIMIT => depth,
+                _ => { return Err(format!("{}: The depth must be between 1 and {}", value, MAX_DEPTH_LIMIT)); }
+            };
+        },
+        "angle" => {
+            settings.angle = match AngleMode::from_name(value) {
+                Some(angle) => angle,
+                None => { return Err(format!("{}: Unknown angle unit, use rad, deg or grad", value)); }
+            };
+        },
+        _ => { return Err(format!("{}: Unknown setting", name)); }
+    }
+    SETTINGS::set(settings);
+    return Ok(format!("{} = {}", name, value));
+}
+
+pub fn get_options() -> Vec<String> {
+    let settings = SETTINGS::get();
+    let mut lines = vec![format!("mode: {}", settings.mode.name())];
+    if let NumberMode::Int(ty) = settings.mode {
+        lines.push(format!("overflow: {}", ty.overflow.name()));
+    }
+    lines.push(format!("display: {}", settings.display.name()));
+    lines.push(format!("base: {}", settings.base.name()));
+    lines.push(format!("depth: {}", settings.max_depth));
+    lines.push(format!("angle: {}", settings.angle.name()));
+    return lines;
+}
+
+// Splits an `expr as hex` like suffix from the line, the `base` setting is used without it
+pub fn split_base_suffix(input: &str) -> (String, Option<OutputBase>) {
+    if let Some((expr, base)) = input.rsplit_once(" as ")
+        && let Some(base) = OutputBase::from_name(base.trim()) {
+        return (expr.trim_end().to_string(), Some(base));
+    }
+    return (input.to_string(), None);
+}
+
+// Numbers without a radix form (fractions, complex numbers) are always shown in decimal
+pub fn format_result(res: &Value, base: Option<OutputBase>) -> String {
+    let res = match res {
+        Value::Number(v) => v,
+        Value::List(items) => {
+            // The items of a list are shown on one line even with `all`
+            let base = match base.unwrap_or(SETTINGS::get().base) {
+                OutputBase::All => OutputBase::Radix(10),
+                base => base
+            };
+            return format!("[{}]", items.iter().map(|item| format_result(item, Some(base))).collect::<Vec<String>>().join(", "));
+        },
+        Value::Function(_) | Value::Builtin(_) => { return res.to_string(); }
+    };
+    let settings = SETTINGS::get();
+    let decimal = match settings.display {
+        RationalDisplay::Fraction => res.to_string(),
+        RationalDisplay::Decimal => res.to_decimal_string(),
+    };
+    return match base.unwrap_or(settings.base) {
+        OutputBase::Radix(10) => decimal,
+        OutputBase::Radix(radix) => res.to_radix_string(radix, false).unwrap_or(decimal),
+        OutputBase::All => match (res.to_radix_string(16, true), res.to_radix_string(8, true), res.to_radix_string(2, true)) {
+            (Some(hex), Some(oct), Some(bin)) => format!("dec {}\nhex {}\noct {}\nbin {}", decimal, hex, oct, bin),
+            _ => decimal
+        },
     };
 }
 
@@ -215,277 +727,735 @@ pub fn write_log(file_path: &'static str) -> Result<(), std::io::Error>{
         .open(file_path)?;
 
     for line in LOG::get().iter() {
-        fp.write(line.as_bytes())?;
-        fp.write("\n".as_bytes())?;
+        fp.write_all(line.as_bytes())?;
+        fp.write_all("\n".as_bytes())?;
     }
     Ok(())
 }
 
-pub fn eval(a: String) -> Result<CalcNumber, ErrorMsg>{
+pub fn eval(a: String) -> Result<Value, CalcError>{
+    return eval_with(a, &SETTINGS::get());
+}
+
+// The recursion of the user-defined functions needs a larger stack than the default of the threads
+const EVAL_STACK_SIZE: usize = 256 * 1024 * 1024;
+
+lazy_static! {
+    static ref EVAL_POOL: rayon::ThreadPool = rayon::ThreadPoolBuilder::new().stack_size(EVAL_STACK_SIZE).build().unwrap();
+}
+
+pub fn eval_with(a: String, settings: &Settings) -> Result<Value, CalcError>{
     let own_input = a.clone();
-    if !is_parens_correct(own_input.chars()) { 
-        return Err((ERROR_MAP[&WRON_PAREN_ERROR].to_string(), None));
+    let (variable, params, start) = split_assignment(&own_input);
+    // A line which is only a name shows its value
+    let Some(i) = start else {
+        return lookup(&variable, settings.mode).ok_or_else(|| CalcError::UnknownVariable(variable.clone(), Span::new(0, variable.len()), suggestion(&variable, &[], false)));
+    };
+    if let Some(err) = assignment_error(&own_input, &variable, &params) {
+        return Err(err);
     }
 
-    // Constructing a word for a var if its in the start of the expression
+    let lexer_in = own_input.chars().skip(i).collect::<String>().trim().to_string();
+    // solving the rest of the input
+    let definition = params.as_deref().map(|params| (variable.as_str(), params));
+    let mut errors: Vec<CalcError> = Vec::new();
+    let tokens = lexer(own_input.clone(), i, settings.mode, definition, &mut errors);
+    missing_operators(&tokens, &mut errors);
+    LOG::add(&format!("Tokens {:?}", tokens));
+    let infixed_tokens = shunting_yard_algorithm(tokens);
+    LOG::add(&format!("Infixed syntax {:?}", infixed_tokens));
+    let root = generate_ast(infixed_tokens, definition.map_or("", |(name, _)| name), &mut errors);
+    LOG::add(&format!("Expression tree {:?}", root));
+    if let Some(err) = errors.into_iter().next() {
+        return Err(err);
+    }
+    // The body of a function is only evaluated when it is called
+    if let Some(params) = params {
+        let text = format!("{}({})", variable, params.join(", "));
+        let lambda = Lambda { params, body: Box::new(root), text };
+        LOG::add(&format!("Function {}", lambda.text));
+        LOG::add("");
+        VARS::add(&variable, ASTNode::Lambda(lambda.clone()), &lexer_in, None);
+        return Ok(Value::Function(lambda));
+    }
+    let res = EVAL_POOL.install(|| root.eval(settings.max_depth, settings))?;
+    LOG::add(&format!("Output {}", res));
+    LOG::add("");
+    if !variable.is_empty() {
+        VARS::add(&variable, root, &lexer_in, Some(res.clone()));
+    }
+    return Ok(res);
+}
+
+pub fn check(a: &str) -> Vec<CalcError> {
+    return check_with(a, &SETTINGS::get());
+}
+
+// Parses the line without evaluating it and collects every error instead of stopping at the first one
+pub fn check_with(a: &str, settings: &Settings) -> Vec<CalcError> {
+    let (variable, params, start) = split_assignment(a);
+    let Some(i) = start else {
+        return match lookup(&variable, settings.mode) {
+            Some(_) => Vec::new(),
+            None => vec![CalcError::UnknownVariable(variable.clone(), Span::new(0, variable.len()), suggestion(&variable, &[], false))]
+        };
+    };
+    let mut errors: Vec<CalcError> = assignment_error(a, &variable, &params).into_iter().collect();
+    let definition = params.as_deref().map(|params| (variable.as_str(), params));
+    let tokens = lexer(a.to_string(), i, settings.mode, definition, &mut errors);
+    missing_operators(&tokens, &mut errors);
+    generate_ast(shunting_yard_algorithm(tokens), definition.map_or("", |(name, _)| name), &mut errors);
+    errors.sort_by_key(|err| err.span().start);
+    return errors;
+}
+
+// Splits a line like `x = ...` or `f(x, y) = ...` into the name, the parameters and the char index of the expression
+// a line without an assignment gives an empty name, a line which is only a name gives no expression
+fn split_assignment(input: &str) -> (String, Option<Vec<String>>, Option<usize>) {
     let mut variable = String::new();
+    let mut params: Option<Vec<String>> = None;
+    let chars: Vec<char> = input.chars().collect();
     let mut i = 0;
-    if !own_input.is_empty() && own_input.chars().nth(i).unwrap().is_ascii_alphabetic(){
-        variable.push(own_input.chars().nth(i).unwrap());
+    if !chars.is_empty() && chars[i].is_ascii_alphabetic(){
+        variable.push(chars[i]);
         i+=1;
-        while i < own_input.len() && (own_input.chars().nth(i).unwrap().is_ascii_alphabetic() || 
-                own_input.chars().nth(i).unwrap().is_digit(10)) {
-            variable.push(own_input.chars().nth(i).unwrap());
+        while i < chars.len() && (chars[i].is_ascii_alphanumeric() ||
+                chars[i] == '_') {
+            variable.push(chars[i]);
             i+=1;
         }
-        while i < own_input.len()  && own_input.chars().nth(i).unwrap().is_whitespace() {
+        while i < chars.len()  && chars[i].is_whitespace() {
             i+=1;
-            
         }
-        if i < own_input.len()  && own_input.char_indices().nth(i).unwrap().1 != '=' {
-            variable.clear();
-            i = 0;
-        }else{
-            if i >= own_input.len() {
-                if let Some(result) = VARS::get_result(&variable) {
-                    return Ok(result);
-                }
-                return Err((format!("{}: {}", variable, ERROR_MAP[&UNKNOWN_TOKEN_ERROR]), Some(0)));
-            }
-            i+=1;
+        // `f(x)` is only a definition when it is followed by `=`, otherwise it is a call
+        if let Some((names, end)) = parse_params(input, i) && chars.get(end) == Some(&'=') {
+            params = Some(names);
+            i = end;
+        }
+        if i < chars.len() && (chars[i] != '=' || chars.get(i+1) == Some(&'=')) {
+            return (String::new(), None, Some(0));
+        }
+        if i >= chars.len() {
+            return (variable, None, None);
         }
+        i+=1;
     }
+    return (variable, params, Some(i));
+}
 
-    let lexer_in = own_input[i..own_input.len()].to_string();
-    // solving the rest of the input
-    match lexer(lexer_in.clone()) {
-        Ok(tokens) => {
-            LOG::add(&format!("Tokens {:?}", tokens));
-            let infixed_tokens = shunting_yard_algorithm(tokens.clone());
-            LOG::add(&format!("Infixed syntax {:?}", infixed_tokens));
-            let expression_tree = generate_ast(infixed_tokens);
-            LOG::add(&format!("Expression tree {:?}", expression_tree));
-            match expression_tree {
-                Ok(root) => {
-                    match root.eval() {
-                        Ok(res) => {
-                            LOG::add(&format!("Output {}", res));
-                            LOG::add(&"".to_string());
-                            if variable.is_empty() {
-                                return Ok(res);
-                            }else{
-                                VARS::add(&variable, root, &lexer_in, Some(res));
-                                return Ok(res);
-                            }
-                        },
-                        Err((err_code, err_idx)) => {
-                            return Err((ERROR_MAP[&err_code].to_string(), err_idx));
-                        }
-                    }
-                },
-                Err((err_code, err_idx)) => Err((ERROR_MAP[&err_code].to_string(), err_idx))
-            }
-        },
-        Err(error) => {
-            return Err((error, None));
+// The value of a name on its own line
+fn lookup(name: &str, mode: NumberMode) -> Option<Value> {
+    if let Some(result) = VARS::get_result(name) {
+        return Some(result.convert(mode));
+    }
+    if let Some(func) = VARS::get_func(name) {
+        return Some(func);
+    }
+    if builtins::is_builtin(name) {
+        return Some(Value::Builtin(name.to_string()));
+    }
+    return constants::get(name, mode).map(Value::Number);
+}
+
+// The known name closest to a misspelled one like `totl`, `functions` only looks at the names which can be called
+fn suggestion(name: &str, params: &[String], functions: bool) -> Option<String> {
+    // The physical constants are only compared by the part after `const.`, the prefix alone would make every one of them close
+    if let Some(rest) = name.strip_prefix("const.") {
+        let known: Vec<String> = constants::CONSTANTS.keys().filter_map(|key| key.strip_prefix("const.")).map(|key| key.to_string()).collect();
+        return suggest::closest(rest, &known).map(|close| format!("const.{}", close));
+    }
+    let mut known = VARS::names(functions);
+    known.extend(params.iter().cloned());
+    known.extend(builtins::BUILTINS.keys().map(|key| key.to_string()));
+    if !functions {
+        known.extend(constants::CONSTANTS.keys().map(|key| key.to_string()));
+    }
+    return suggest::closest(name, &known);
+}
+
+// A user definition replaces the built-in function of its name, `definition` is the function being defined
+fn is_user_function(name: &str, definition: &str) -> bool {
+    return !builtins::is_reserved(name) && (name == definition || VARS::get_func(name).is_some());
+}
+
+// The names which cannot be assigned and the repeated parameters of a definition
+fn assignment_error(input: &str, variable: &str, params: &Option<Vec<String>>) -> Option<CalcError> {
+    if constants::is_constant(variable) {
+        return Some(CalcError::Constant(variable.to_string(), Span::new(0, variable.len())));
+    }
+    if params.is_some() && builtins::is_reserved(variable) {
+        return Some(CalcError::ReservedName(variable.to_string(), Span::new(0, variable.len())));
+    }
+    if params.is_some() && let Some(open) = input.chars().position(|c| c == '(') {
+        return repeated_param(input, open);
+    }
+    return None;
+}
+
+// Reads a `(x, y)` parameter list starting at `i`, returns the names and the index after the spaces following `)`
+fn parse_params(input: &str, i: usize) -> Option<(Vec<String>, usize)> {
+    if input.chars().nth(i) != Some('(') { return None; }
+    let inner: String = input.chars().skip(i+1).take_while(|c| *c != ')').collect();
+    let mut end = i + inner.chars().count() + 2;
+    if input.chars().nth(end - 1) != Some(')') { return None; }
+    let mut names: Vec<String> = Vec::new();
+    if !inner.trim().is_empty() {
+        for name in inner.split(',').map(|name| name.trim()) {
+            let valid = name.starts_with(|c: char| c.is_ascii_alphabetic()) && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid { return None; }
+            names.push(name.to_string());
         }
     }
+    while input.chars().nth(end).is_some_and(|c| c.is_whitespace()) {
+        end += 1;
+    }
+    return Some((names, end));
 }
 
 
+// The second one of a repeated parameter like the last `x` of `f(x, x) = 1`, the list starts with the `(` at `open`
+fn repeated_param(input: &str, open: usize) -> Option<CalcError> {
+    let mut names: Vec<String> = Vec::new();
+    let mut start = open + 1;
+    for name in input.chars().skip(open+1).take_while(|c| *c != ')').collect::<String>().split(',') {
+        let trimmed = name.trim();
+        let name_start = start + name.chars().take_while(|c| c.is_whitespace()).count();
+        if names.iter().any(|n| n == trimmed) {
+            return Some(CalcError::DuplicateParam(trimmed.to_string(), Span::new(name_start, name_start + trimmed.chars().count())));
+        }
+        names.push(trimmed.to_string());
+        start += name.chars().count() + 1;
+    }
+    return None;
+}
 
 fn op_precedence(token: TOKEN) -> u32 {
     match token {
-        TOKEN::EXPR(_, _) => 8,
-        TOKEN::FACT(_) => 7,
-        TOKEN::POW(_) | TOKEN::SQRT(_) => 6,
-        TOKEN::DIV(_) | TOKEN::MUL(_) | TOKEN::MOD(_) => 5,
-        TOKEN::PLUS(_) | TOKEN::MINUS(_) => 4,
+        TOKEN::EXPR(_, _) => 9,
+        TOKEN::FACT(_) => 8,
+        TOKEN::POW(_) | TOKEN::SQRT(_) | TOKEN::NEG(_) | TOKEN::BNOT(_) => 7,
+        TOKEN::DIV(_) | TOKEN::MUL(_) | TOKEN::MOD(_) | TOKEN::MATMUL(_) => 6,
+        TOKEN::PLUS(_) | TOKEN::MINUS(_) | TOKEN::CONCAT(_) => 5,
+        TOKEN::SHL(_) | TOKEN::SHR(_) | TOKEN::ROL(_) | TOKEN::ROR(_) |
+        TOKEN::RANGE(_) | TOKEN::STEP(_) => 4,
         TOKEN::EQUAL(_) | TOKEN::NOT(_) | TOKEN::NEQUAL(_) |
         TOKEN::GT(_) | TOKEN::GE(_) | TOKEN::LT(_) | TOKEN::LE(_) => 3,
         TOKEN::AND(_) | TOKEN::BAND(_) => 2,
         TOKEN::OR(_) | TOKEN::BOR(_) | TOKEN::XOR(_) | TOKEN::BXOR(_) => 1,
-        TOKEN::LPAREN(_) | TOKEN::RPAREN(_) | TOKEN::NUMBER(_, _) => 0
+        TOKEN::LPAREN(_) | TOKEN::RPAREN(_) | TOKEN::COMMA(_) |
+        TOKEN::NUMBER(_, _) | TOKEN::FUNC(_, _, _) | TOKEN::INDEX(_, _, _) | TOKEN::PARAM(_, _) |
+        TOKEN::QUESTION(_) | TOKEN::COLON(_) | TOKEN::TERNARY(_) => 0
     }
 }
 
 fn op_associative(token: TOKEN) -> Associativity {
     match token {
         TOKEN::LPAREN(_) | TOKEN::RPAREN(_) => Associativity::NOT,
-        TOKEN::NUMBER(_, _) => unreachable!(),
-        TOKEN::POW(_) | TOKEN::SQRT(_) | TOKEN::NOT(_) => Associativity::RIGHT,
+        TOKEN::NUMBER(_, _) | TOKEN::PARAM(_, _) => unreachable!(),
+        TOKEN::POW(_) | TOKEN::SQRT(_) | TOKEN::NOT(_) | TOKEN::NEG(_) | TOKEN::BNOT(_) |
+        TOKEN::QUESTION(_) | TOKEN::TERNARY(_) => Associativity::RIGHT,
         _ => Associativity::LEFT
     }
 }
 
-fn factorial(n: CalcNumber) -> CalcNumber {
-    let mut res = Wrapping(1);
-    if n.0 == 0 || n.0 == 1 { return res; }
-    for i in 1..n.0+1 { res *= i; }
-    return res;
+// Every operator the lexer reads, the suggestions for an unknown token are taken from them
+pub(crate) const OPERATORS: [&str; 30] = [
+    "+", "++", "-", "*", "**", "/", "%", "!", "@", "|", "||", "&", "&&", "^", "^^",
+    "..", "==", "~", "~=", ">", ">>", ">>>", ">=", "<", "<<", "<<<", "<=", "->", "?", ":",
+];
+
+// A minus sign is a negation when there is no operand before it
+fn is_unary_position(prev: Option<&TOKEN>) -> bool {
+    return !matches!(prev, Some(TOKEN::NUMBER(_, _)) | Some(TOKEN::EXPR(_, _)) | Some(TOKEN::PARAM(_, _)) |
+        Some(TOKEN::RPAREN(_)) | Some(TOKEN::FACT(_)));
 }
 
-fn to_bool(n: CalcNumber) -> bool {
-    if n.0 > 0 { return true; }
-    return false;
+// An operand right after another one like `1e` or `f(1)(2)` has no operator between them, the tree would drop one of them
+// an error between them like an unknown operator or an operand which could not be read is already reported
+fn missing_operators(tokens: &[TOKEN], errors: &mut Vec<CalcError>) {
+    for pair in tokens.windows(2) {
+        let char_idx = match pair[1] {
+            TOKEN::NUMBER(_, char_idx) | TOKEN::EXPR(_, char_idx) | TOKEN::PARAM(_, char_idx) |
+            TOKEN::FUNC(_, _, char_idx) | TOKEN::LPAREN(char_idx) => char_idx,
+            _ => { continue; }
+        };
+        let reported = errors.iter().any(|err| (pair[0].char_idx()..char_idx).contains(&err.span().start));
+        if !is_unary_position(Some(&pair[0])) && !reported {
+            errors.push(CalcError::UnexpectedToken(Span::at(char_idx)));
+        }
+    }
+}
+
+// Checks `pat` at the char index `i`, used for the multi char operators like `<<<`
+fn starts_with_at(input: &str, i: usize, pat: &str) -> bool {
+    return input.chars().skip(i).take(pat.chars().count()).eq(pat.chars());
 }
 
-fn to_calc_num(b: bool) -> CalcNumber {
-    if b { return Wrapping(1); }
-    return Wrapping(0);
+// Returns the radix of a `0x`, `0b` or `0o` prefixed literal starting at `i`
+fn radix_prefix(input: &str, i: usize) -> Option<u32> {
+    if input.chars().nth(i) != Some('0') { return None; }
+    let radix = match input.chars().nth(i+1) {
+        Some('x') | Some('X') => 16,
+        Some('b') | Some('B') => 2,
+        Some('o') | Some('O') => 8,
+        _ => { return None; }
+    };
+    return match input.chars().nth(i+2) {
+        Some(c) if c.is_digit(radix) => Some(radix),
+        _ => None
+    };
+}
+
+// Checks if the characters from `i` are the exponent digits of a literal like `1e-9`
+fn is_exponent(input: &str, i: usize) -> bool {
+    return match input.chars().nth(i) {
+        Some('+') | Some('-') => input.chars().nth(i+1).is_some_and(|c| c.is_ascii_digit()),
+        Some(c) => c.is_ascii_digit(),
+        None => false
+    };
 }
 
-fn lexer(input: String) -> Result<Vec<TOKEN>, String> {
+// Tokenizes the input from the `start` char, the tokens store their char index in the whole input
+// `definition` is the name and the parameters of the function whose body is read, so it can call itself
+// the errors are collected in `errors` and the rest of the line is still read, so every one of them can be shown
+fn lexer(input: String, start: usize, mode: NumberMode, definition: Option<(&str, &[String])>, errors: &mut Vec<CalcError>) -> Vec<TOKEN> {
+    let (name, params) = definition.unwrap_or_default();
     let mut tokens: Vec<TOKEN> = Vec::new();
-    let mut i: usize = 0;
+    // Whether the open brackets are slices like `v[1:3]`, their first `:` separates the bounds
+    let mut slices: Vec<bool> = Vec::new();
+    // The open parentheses and brackets with their char index, every closing one must match the last
+    let mut parens: Vec<(char, usize)> = Vec::new();
+    let chars: Vec<char> = input.chars().collect();
+    let mut i: usize = start;
     let mut nc: char;
-    let mut num_of_spaces: usize = 0;
-    while i < input.len() {
-        nc = input.chars().nth(i).unwrap();
+    while i < chars.len() {
+        nc = chars[i];
         if nc != ' ' {
             let og_i = i;
             match nc {
-                '+' => tokens.push(TOKEN::PLUS(i)),
-                '-' => tokens.push(TOKEN::MINUS(i)),
+                '+' => {
+                    if i+1 < chars.len() && chars[i+1] == '+' {
+                        i+=1;
+                        tokens.push(TOKEN::CONCAT(i-1));
+                    }else{
+                        tokens.push(TOKEN::PLUS(i));
+                    }
+                },
+                '-' => {
+                    if is_unary_position(tokens.last()) {
+                        tokens.push(TOKEN::NEG(i));
+                    }else{
+                        tokens.push(TOKEN::MINUS(i));
+                    }
+                },
                 '*' => {
-                    if i+1 < input.len() && input.chars().nth(i+1).unwrap() == '*' {
+                    if i+1 < chars.len() && chars[i+1] == '*' {
                         i+=1;
-                        tokens.push(TOKEN::POW(i-1+num_of_spaces));
+                        tokens.push(TOKEN::POW(i-1));
+                    }else{
+                        tokens.push(TOKEN::MUL(i));
+                    }
+                },
+                '(' => {
+                    // `(x, y) -> x + y` is a function value
+                    if let Some((names, arrow)) = parse_params(&input, i) && starts_with_at(&input, arrow, "->") {
+                        errors.extend(repeated_param(&input, i));
+                        let (lambda, end) = lex_lambda(&input, i, names, arrow+2, mode, (name, params), errors);
+                        tokens.push(TOKEN::EXPR(Box::new(lambda), og_i));
+                        i = end-1;
+                    }else{
+                        tokens.push(TOKEN::LPAREN(i));
+                        slices.push(false);
+                        parens.push((nc, i));
+                    }
+                },
+                '[' => {
+                    parens.push((nc, i));
+                    if is_unary_position(tokens.last()) {
+                        // `[1, 2, 3]` is a list literal
+                        tokens.push(TOKEN::FUNC("list".to_string(), 0, i));
+                        tokens.push(TOKEN::LPAREN(i));
+                        slices.push(false);
+                    }else{
+                        let slice = is_slice(&input, i);
+                        tokens.push(TOKEN::INDEX(if slice { "slice" } else { "at" }.to_string(), 0, i));
+                        tokens.push(TOKEN::LPAREN(i));
+                        slices.push(slice);
+                        // A missing start bound like `v[:2]` starts from the first item
+                        if slice && input.chars().skip(i+1).find(|c| *c != ' ') == Some(':') {
+                            tokens.push(TOKEN::NUMBER(CalcNumber::parse("0", mode, Span::at(i)).unwrap(), i));
+                        }
+                    }
+                },
+                ',' => tokens.push(TOKEN::COMMA(i)),
+                '?' => tokens.push(TOKEN::QUESTION(i)),
+                ':' => {
+                    // A missing end bound like `v[1:]` is left out of the arguments
+                    if let Some(slice) = slices.last_mut() && *slice {
+                        *slice = false;
+                        if input.chars().skip(i+1).find(|c| *c != ' ') != Some(']') {
+                            tokens.push(TOKEN::COMMA(i));
+                        }
+                    }else{
+                        tokens.push(TOKEN::COLON(i));
+                    }
+                },
+                ')' | ']' => {
+                    match parens.pop() {
+                        None => errors.push(CalcError::UnopenedParen(nc, Span::at(i))),
+                        Some((open, open_idx)) => {
+                            if (open == '(') != (nc == ')') {
+                                errors.push(CalcError::MismatchedParen(nc, Span::at(open_idx), Span::at(i)));
+                            }
+                            slices.pop();
+                            tokens.push(TOKEN::RPAREN(i));
+                        }
+                    }
+                },
+                '/' => tokens.push(TOKEN::DIV(i)),
+                '!' => {
+                    // A prefix `!` is the bitwise complement, a postfix one is the factorial
+                    if is_unary_position(tokens.last()) {
+                        tokens.push(TOKEN::BNOT(i));
                     }else{
-                        tokens.push(TOKEN::MUL(i+num_of_spaces));
+                        tokens.push(TOKEN::FACT(i));
+                    }
+                },
+                '@' => {
+                    // A prefix `@` is the square root, an infix one is the matrix multiplication
+                    if is_unary_position(tokens.last()) {
+                        tokens.push(TOKEN::SQRT(i));
+                    }else{
+                        tokens.push(TOKEN::MATMUL(i));
                     }
                 },
-                '(' => tokens.push(TOKEN::LPAREN(i+num_of_spaces)),
-                ')' => tokens.push(TOKEN::RPAREN(i+num_of_spaces)),
-                '/' => tokens.push(TOKEN::DIV(i+num_of_spaces)),
-                '!' => tokens.push(TOKEN::FACT(i+num_of_spaces)),
-                '@' => tokens.push(TOKEN::SQRT(i+num_of_spaces)),
                 '|' => {
-                    if i+1 < input.len() && input.chars().nth(i+1).unwrap() == '|' {
+                    if i+1 < chars.len() && chars[i+1] == '|' {
                         i+=1;
-                        tokens.push(TOKEN::OR(i-1+num_of_spaces));
+                        tokens.push(TOKEN::OR(i-1));
                     }else{
-                        tokens.push(TOKEN::BOR(i+num_of_spaces));
+                        tokens.push(TOKEN::BOR(i));
                     }
                 },
                 '&' => {
-                    if i+1 < input.len() && input.chars().nth(i+1).unwrap() == '&' {
+                    if i+1 < chars.len() && chars[i+1] == '&' {
                         i+=1;
-                        tokens.push(TOKEN::AND(i-1+num_of_spaces));
+                        tokens.push(TOKEN::AND(i-1));
                     }else{
-                        tokens.push(TOKEN::BAND(i+num_of_spaces));
+                        tokens.push(TOKEN::BAND(i));
                     }
                 },
                 '^' => {
-                    if i+1 < input.len() && input.chars().nth(i+1).unwrap() == '^' {
+                    if i+1 < chars.len() && chars[i+1] == '^' {
                         i+=1;
-                        tokens.push(TOKEN::XOR(i-1+num_of_spaces));
+                        tokens.push(TOKEN::XOR(i-1));
                     }else{
-                        tokens.push(TOKEN::BXOR(i+num_of_spaces));
+                        tokens.push(TOKEN::BXOR(i));
                     }
                 },
                 '%' => tokens.push(TOKEN::MOD(i)),
+                '.' if starts_with_at(&input, i, "..") => {
+                    i+=1;
+                    tokens.push(TOKEN::RANGE(i-1));
+                },
                 '=' => {
-                    if i+1 < input.len() && input.chars().nth(i+1).unwrap() == '=' {
+                    if i+1 < chars.len() && chars[i+1] == '=' {
                         i+=1;
-                        tokens.push(TOKEN::EQUAL(i-1+num_of_spaces));
+                        tokens.push(TOKEN::EQUAL(i-1));
+                    }else if i > 0 && tokens.last() == Some(&TOKEN::FACT(i-1)) {
+                        // `!=` is read as a factorial first
+                        tokens.pop();
+                        errors.push(CalcError::UnknownToken("!=".to_string(), Span::new(i-1, i+1)));
+                    }else if let Some(next @ ('<' | '>' | '~')) = chars.get(i+1).copied() {
+                        i+=1;
+                        errors.push(CalcError::UnknownToken(format!("={}", next), Span::new(i-1, i+1)));
                     }else {
-                        return Err(format!("{}: {}", nc, ERROR_MAP[&UNKNOWN_TOKEN_ERROR])); 
+                        errors.push(CalcError::UnknownToken(nc.to_string(), Span::at(i)));
                     }
                 },
                 '~' => {
-                    if i+1 < input.len() && input.chars().nth(i+1).unwrap() == '=' {
+                    if i+1 < chars.len() && chars[i+1] == '=' {
                         i+=1;
-                        tokens.push(TOKEN::NEQUAL(i-1+num_of_spaces));
+                        tokens.push(TOKEN::NEQUAL(i-1));
                     }else{
-                        tokens.push(TOKEN::NOT(i+num_of_spaces));
+                        tokens.push(TOKEN::NOT(i));
                     }
                 },
                 '>' => {
-                    if i+1 < input.len() && input.chars().nth(i+1).unwrap() == '=' {
+                    if starts_with_at(&input, i, ">>>") {
+                        i+=2;
+                        tokens.push(TOKEN::ROR(i-2));
+                    }else if starts_with_at(&input, i, ">>") {
+                        i+=1;
+                        tokens.push(TOKEN::SHR(i-1));
+                    }else if i+1 < chars.len() && chars[i+1] == '=' {
                         i+=1;
                         tokens.push(TOKEN::GE(i-1));
                     }else{
-                        tokens.push(TOKEN::GT(i+num_of_spaces));
+                        tokens.push(TOKEN::GT(i));
                     }
                 },
                 '<' => {
-                    if i+1 < input.len() && input.chars().nth(i+1).unwrap() == '=' {
+                    if starts_with_at(&input, i, "<<<") {
+                        i+=2;
+                        tokens.push(TOKEN::ROL(i-2));
+                    }else if starts_with_at(&input, i, "<<") {
                         i+=1;
-                        tokens.push(TOKEN::LE(i-1+num_of_spaces));
+                        tokens.push(TOKEN::SHL(i-1));
+                    }else if i+1 < chars.len() && chars[i+1] == '=' {
+                        i+=1;
+                        tokens.push(TOKEN::LE(i-1));
                     }else{
-                        tokens.push(TOKEN::LT(i+num_of_spaces));
+                        tokens.push(TOKEN::LT(i));
                     }
                 },
                 _ => {
-                    if nc.is_digit(10) {
+                    if let Some(radix) = radix_prefix(&input, i) {
+                        // Hexadecimal, binary or octal literal like `0x1F`, `0b1011` or `0o755`
+                        i += 1;
+                        while i+1<chars.len() && (chars[i+1].is_digit(radix) || chars[i+1] == '_') {
+                            i += 1;
+                        }
+                        let literal: String = chars[og_i..=i].iter().collect();
+                        match CalcNumber::from_radix(&literal, radix, mode, Span::new(og_i, i+1)) {
+                            Ok(num) => tokens.push(TOKEN::NUMBER(num, og_i)),
+                            Err(err) => {
+                                errors.push(err);
+                                tokens.push(TOKEN::EXPR(Box::new(placeholder()), og_i));
+                            }
+                        }
+                    } else if nc.is_ascii_digit() || (nc == '.' && chars.get(i+1).is_some_and(|c| c.is_ascii_digit())) {
                         let mut number:String = String::new();
                         number.push(nc);
-                        while i+1<input.len() {
-                            nc = input.chars().nth(i+1).unwrap();
-                            if nc.is_digit(10) {
-                                number.push(input.chars().nth(i+1).unwrap());
-                            }else if !(nc.is_digit(10) || nc == ' ') {
+                        while i+1<chars.len() {
+                            nc = chars[i+1];
+                            if nc.is_ascii_digit() || (nc == '.' && !number.contains(['.', 'e', 'E']) && !starts_with_at(&input, i+1, "..")) {
+                                number.push(nc);
+                            }else if (nc == 'e' || nc == 'E') && !number.contains(['e', 'E']) && is_exponent(&input, i+2) {
+                                number.push(nc);
+                                nc = chars[i+2];
+                                if nc == '+' || nc == '-' {
+                                    number.push(nc);
+                                    i += 1;
+                                }
+                            }else if nc == 'i' && !chars.get(i+2).is_some_and(|c| c.is_ascii_alphanumeric()) {
+                                // Imaginary literal like `3i`
+                                number.push(nc);
+                                i += 1;
+                                break;
+                            }else if nc != ' ' && nc != '_' {
                                 break;
                             }
                             i += 1;
                         }
-                        tokens.push(TOKEN::NUMBER(Wrapping(number.parse().unwrap()), og_i+num_of_spaces));
+                        // The smallest signed integer like `-128` in i8 is read as one literal, `-128**2` is still `-(128**2)`
+                        let min = match tokens.last() {
+                            Some(TOKEN::NEG(neg_idx)) if !chars[i+1..].iter().collect::<String>().trim_start().starts_with("**") => {
+                                CalcNumber::parse_min(&number, mode).map(|num| (num, *neg_idx))
+                            },
+                            _ => None
+                        };
+                        if let Some((num, neg_idx)) = min {
+                            tokens.pop();
+                            tokens.push(TOKEN::NUMBER(num, neg_idx));
+                        }else{
+                            match CalcNumber::parse(&number, mode, Span::new(og_i, i+1)) {
+                                Ok(num) => tokens.push(TOKEN::NUMBER(num, og_i)),
+                                Err(err) => {
+                                    errors.push(err);
+                                    tokens.push(TOKEN::EXPR(Box::new(placeholder()), og_i));
+                                }
+                            }
+                        }
                     } else if nc.is_ascii_alphabetic() {
                         let mut var: String = String::from(nc);
-                        while i+1<input.len() {
-                            nc = input.chars().nth(i+1).unwrap();
-                            if nc.is_digit(10) || nc.is_ascii_alphabetic() {
+                        while i+1<chars.len() {
+                            nc = chars[i+1];
+                            if nc.is_ascii_alphanumeric() || nc == '_' {
                                 var.push(nc);
                             } else {
                                 break;
                             }
                             i += 1;
                         }
-                        if let Some(val) = VARS::get_result(&var) {
-                            tokens.push(TOKEN::NUMBER(val, og_i+num_of_spaces));
+                        // The physical constants are written like `const.c`
+                        if var == "const" && chars.get(i+1) == Some(&'.') {
+                            var.push('.');
+                            i += 1;
+                            while i+1<chars.len() && (chars[i+1].is_ascii_alphanumeric() || chars[i+1] == '_') {
+                                var.push(chars[i+1]);
+                                i += 1;
+                            }
+                        }
+                        let next = i+1 + input.chars().skip(i+1).take_while(|c| *c == ' ').count();
+                        if starts_with_at(&input, next, "->") {
+                            // `x -> x*2` is a function value
+                            let (lambda, end) = lex_lambda(&input, og_i, vec![var], next+2, mode, (name, params), errors);
+                            tokens.push(TOKEN::EXPR(Box::new(lambda), og_i));
+                            i = end-1;
+                        }else if chars.get(next) == Some(&'(') {
+                            if !params.contains(&var) && !builtins::is_builtin(&var) && VARS::get_func(&var).is_none() && var != name {
+                                let mut known = params.to_vec();
+                                known.extend((!name.is_empty()).then(|| name.to_string()));
+                                errors.push(CalcError::UnknownFunction(var.clone(), Span::new(og_i, i+1), suggestion(&var, &known, true)));
+                            }
+                            let reduction = (var == "sum" || var == "prod") && !params.contains(&var);
+                            tokens.push(TOKEN::FUNC(var, 0, og_i));
+                            // `sum(i, 1..10, i**2)` binds `i` in the rest of the call
+                            if reduction && let Some((bound, comma, close)) = bound_variable(&input, next) {
+                                tokens.push(TOKEN::LPAREN(next));
+                                tokens.push(TOKEN::PARAM(bound.clone(), next+1 + input.chars().skip(next+1).take_while(|c| *c == ' ').count()));
+                                tokens.push(TOKEN::COMMA(comma));
+                                // The bound values cannot use the variable itself, like `sum(i, 1..i, i)`
+                                let over_end = top_level_comma(&input, comma+1, close).unwrap_or(close);
+                                tokens.extend(lexer(input.chars().take(over_end).collect(), comma+1, mode, Some((name, params)), errors));
+                                if over_end < close {
+                                    let mut scope = params.to_vec();
+                                    scope.push(bound);
+                                    tokens.push(TOKEN::COMMA(over_end));
+                                    tokens.extend(lexer(input.chars().take(close).collect(), over_end+1, mode, Some((name, &scope)), errors));
+                                }
+                                match chars.get(close).copied() {
+                                    Some(')') => {},
+                                    Some(c) => errors.push(CalcError::MismatchedParen(c, Span::at(next), Span::at(close))),
+                                    None => errors.push(CalcError::UnclosedParen('(', Span::at(next)))
+                                }
+                                tokens.push(TOKEN::RPAREN(close));
+                                i = close;
+                            }
+                        }else if var == "step" && !is_unary_position(tokens.last()) {
+                            tokens.push(TOKEN::STEP(og_i));
+                        }else if params.contains(&var) {
+                            tokens.push(TOKEN::PARAM(var, og_i));
+                        }else if var == "i" && mode == NumberMode::Complex {
+                            tokens.push(TOKEN::NUMBER(CalcNumber::imaginary_unit(), og_i));
+                        }else if let Some(val) = constants::get(&var, mode) {
+                            tokens.push(TOKEN::NUMBER(val, og_i));
+                        }else if let Some(val @ Value::Number(_)) = VARS::get_result(&var) {
+                            let Value::Number(val) = val.convert(mode) else { unreachable!() };
+                            tokens.push(TOKEN::NUMBER(val, og_i));
+                        }else if let Some(val) = VARS::get_result(&var).or_else(|| VARS::get_func(&var)) {
+                            tokens.push(TOKEN::EXPR(Box::new(val.convert(mode).to_node()), og_i));
+                        }else if builtins::is_builtin(&var) {
+                            tokens.push(TOKEN::EXPR(Box::new(ASTNode::Value(Value::Builtin(var))), og_i));
+                        }else if let Some(expr) = VARS::get_expr(&var) {
+                            tokens.push(TOKEN::EXPR(Box::new(expr), og_i));
                         }else{
-                            tokens.push(TOKEN::EXPR(match VARS::get_expr(&var) {
-                                Some(expr) => Box::new(expr),
-                                None => { return Err(format!("{}: {}", var.clone(), ERROR_MAP[&UNKNOWN_TOKEN_ERROR])); }
-                            }, og_i+num_of_spaces));
+                            let close = suggestion(&var, params, false);
+                            errors.push(CalcError::UnknownVariable(var, Span::new(og_i, i+1), close));
+                            tokens.push(TOKEN::EXPR(Box::new(placeholder()), og_i));
                         }
                     } else {
-                        return Err(format!("{}: {}", nc, ERROR_MAP[&UNKNOWN_TOKEN_ERROR])); 
+                        errors.push(CalcError::UnknownToken(nc.to_string(), Span::at(i)));
+                        // In place of an operand it stands for one, so the operator before it is not reported too
+                        if is_unary_position(tokens.last()) {
+                            tokens.push(TOKEN::EXPR(Box::new(placeholder()), og_i));
+                        }
                     }
                 }
             }
-        }else{
-            num_of_spaces += 1;
         }
         i+=1;
     }
+    // The last open one is the most likely to miss its pair, the missing pairs are added so the calls still get their arguments
+    for (open, open_idx) in parens.into_iter().rev() {
+        errors.push(CalcError::UnclosedParen(open, Span::at(open_idx)));
+        tokens.push(TOKEN::RPAREN(chars.len()));
+    }
 
-    return Ok(tokens);
+    return tokens;
 }
 
-fn is_parens_correct(chrs: Chars) -> bool {
-    let mut paren_stack: VecDeque<char> = VecDeque::new();
-    for chr in chrs {
-        match chr {
-            '(' => paren_stack.push_back(chr),
-            ')' => {
-                if paren_stack.is_empty() { return false; }
-                _ = paren_stack.pop_back();
-            },
+// Lexes the body of a `x -> ...` function value until a `,` or a closing parenthesis outside of it
+// returns the function and the char index after its body
+fn lex_lambda(input: &str, start: usize, names: Vec<String>, body_start: usize, mode: NumberMode, definition: (&str, &[String]), errors: &mut Vec<CalcError>) -> (ASTNode, usize) {
+    let (name, params) = definition;
+    let mut depth = 0;
+    let mut end = body_start;
+    for c in input.chars().skip(body_start) {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' | ',' if depth == 0 => break,
+            ')' | ']' => depth -= 1,
+            _ => {}
+        }
+        end += 1;
+    }
+    // The body sees the parameters of the outer function too
+    let mut scope = params.to_vec();
+    scope.extend(names.iter().cloned());
+    let tokens = lexer(input.chars().take(end).collect(), body_start, mode, Some((name, &scope)), errors);
+    missing_operators(&tokens, errors);
+    let body = generate_ast(shunting_yard_algorithm(tokens), name, errors);
+    let text = input.chars().skip(start).take(end - start).collect::<String>().trim().to_string();
+    return (ASTNode::Lambda(Lambda { params: names, body: Box::new(body), text }), end);
+}
+
+// Reads the `i,` after the `(` at `i` of a `sum(i, 1..10, i**2)` call
+// returns the variable, the index of its comma and the index of the closing parenthesis or the end of the input
+fn bound_variable(input: &str, i: usize) -> Option<(String, usize, usize)> {
+    let var: String = input.chars().skip(i+1).take_while(|c| *c != ',').collect();
+    let var = var.trim();
+    let valid = var.starts_with(|c: char| c.is_ascii_alphabetic()) && var.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid { return None; }
+    let comma = i+1 + input.chars().skip(i+1).take_while(|c| *c != ',').count();
+    let mut depth = 0;
+    for (close, c) in input.chars().enumerate().skip(comma+1) {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' if depth == 0 => { return Some((var.to_string(), comma, close)); },
+            ')' | ']' => depth -= 1,
+            _ => {}
+        }
+    }
+    return Some((var.to_string(), comma, input.chars().count()));
+}
+
+// The first `,` between `start` and `end` which is not inside of brackets
+fn top_level_comma(input: &str, start: usize, end: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (k, c) in input.chars().enumerate().take(end).skip(start) {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ',' if depth == 0 => { return Some(k); },
             _ => {}
         }
     }
-    return paren_stack.is_empty();
+    return None;
+}
+
+// Checks if the characters from the `[` at `i` are a slice like `[1:3]`, a `?` makes the `:` a conditional
+fn is_slice(input: &str, i: usize) -> bool {
+    let mut depth = 0;
+    for c in input.chars().skip(i+1) {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' if depth == 0 => { return false; },
+            ')' | ']' => depth -= 1,
+            '?' if depth == 0 => { return false; },
+            ':' if depth == 0 => { return true; },
+            _ => {}
+        }
+    }
+    return false;
 }
 
 fn shunting_yard_algorithm(tokens: Vec<TOKEN>) -> VecDeque<TOKEN> {
     let mut output: VecDeque<TOKEN> = VecDeque::new();
     let mut operators: Vec<TOKEN> = Vec::new();
+    // Number of arguments inside every open parenthesis, the function calls read it
+    let mut arg_counts: Vec<usize> = Vec::new();
 
     for token in tokens {
+        if !matches!(token, TOKEN::RPAREN(_) | TOKEN::COMMA(_)) && let Some(count) = arg_counts.last_mut() && *count == 0 {
+            *count = 1;
+        }
         match token {
-            TOKEN::LPAREN(_) => operators.push(token),
+            TOKEN::LPAREN(_) => {
+                operators.push(token);
+                arg_counts.push(0);
+            },
             TOKEN::RPAREN(_) => {
                 while let Some(op) = operators.last() {
                     if let TOKEN::LPAREN(_) = *op { 
@@ -494,8 +1464,43 @@ fn shunting_yard_algorithm(tokens: Vec<TOKEN>) -> VecDeque<TOKEN> {
                     }
                     output.push_back(operators.pop().unwrap());
                 }
+                let count = arg_counts.pop().unwrap_or(0);
+                match operators.last() {
+                    Some(TOKEN::FUNC(name, _, func_idx)) => output.push_back(TOKEN::FUNC(name.clone(), count, *func_idx)),
+                    Some(TOKEN::INDEX(name, _, index_idx)) => output.push_back(TOKEN::INDEX(name.clone(), count, *index_idx)),
+                    _ => { continue; }
+                }
+                operators.pop();
+            },
+            TOKEN::COMMA(_) => {
+                while let Some(op) = operators.last() {
+                    if let TOKEN::LPAREN(_) = *op { break; }
+                    output.push_back(operators.pop().unwrap());
+                }
+                // A comma list is only allowed as the arguments of a function
+                let in_call = operators.len() >= 2 && matches!(operators[operators.len()-2], TOKEN::FUNC(_, _, _) | TOKEN::INDEX(_, _, _));
+                match arg_counts.last_mut() {
+                    Some(count) if in_call => *count = (*count).max(1) + 1,
+                    _ => output.push_back(token),
+                }
+            },
+            TOKEN::NUMBER(_, _) | TOKEN::PARAM(_, _) | TOKEN::EXPR(_, _) => output.push_back(token),
+            TOKEN::FUNC(_, _, _) | TOKEN::INDEX(_, _, _) => operators.push(token),
+            TOKEN::COLON(_) => {
+                while let Some(op) = operators.last() {
+                    if matches!(op, TOKEN::LPAREN(_) | TOKEN::QUESTION(_)) { break; }
+                    output.push_back(operators.pop().unwrap());
+                }
+                match operators.last() {
+                    Some(TOKEN::QUESTION(char_idx)) => {
+                        let char_idx = *char_idx;
+                        operators.pop();
+                        operators.push(TOKEN::TERNARY(char_idx));
+                    },
+                    // A `:` without `?` is reported when the tree is built
+                    _ => output.push_back(token),
+                }
             },
-            TOKEN::NUMBER(_, _) => output.push_back(token),
             _ => {
                 while let Some(op) = operators.last() {
                     let o1 = token.clone();
@@ -523,7 +1528,9 @@ fn shunting_yard_algorithm(tokens: Vec<TOKEN>) -> VecDeque<TOKEN> {
     
 }
 
-fn generate_ast(tokens: VecDeque<TOKEN>) -> Result<ASTNode, ErrorCode> {
+// A missing operand is replaced by a placeholder after its error is collected, so the rest of the tree is still checked
+// `definition` is the name of the function whose body is read
+fn generate_ast(tokens: VecDeque<TOKEN>, definition: &str, errors: &mut Vec<CalcError>) -> ASTNode {
     let mut stack: Vec<ASTNode> = Vec::new();
 
     for token in tokens {
@@ -537,41 +1544,127 @@ fn generate_ast(tokens: VecDeque<TOKEN>) -> Result<ASTNode, ErrorCode> {
             TOKEN::BXOR(char_idx) | TOKEN::MOD(char_idx) | 
             TOKEN::EQUAL(char_idx) | TOKEN::NEQUAL(char_idx) |
             TOKEN::GT(char_idx) | TOKEN::GE(char_idx) |
-            TOKEN::LT(char_idx) | TOKEN::LE(char_idx) => {
-                let left = Box::new(match stack.pop(){
-                    Some(v) => v,
-                    None => { return Err((ARG_MISS_ERROR, Some(char_idx))); }
-                });
-                let right = Box::new(match stack.pop() {
-                    Some(v) => v,
-                    None => { return Err((ARG_MISS_ERROR, Some(char_idx))); }
-                });
-                stack.push(ASTNode::BOperator { op: token, left: right, right: left })
+            TOKEN::LT(char_idx) | TOKEN::LE(char_idx) |
+            TOKEN::SHL(char_idx) | TOKEN::SHR(char_idx) |
+            TOKEN::ROL(char_idx) | TOKEN::ROR(char_idx) |
+            TOKEN::CONCAT(char_idx) | TOKEN::MATMUL(char_idx) |
+            TOKEN::RANGE(char_idx) | TOKEN::STEP(char_idx) => {
+                let left = stack.pop();
+                let right = stack.pop();
+                if right.is_none() { errors.push(CalcError::MissingOperand(Span::at(char_idx))); }
+                let left = Box::new(left.unwrap_or_else(placeholder));
+                let right = Box::new(right.unwrap_or_else(placeholder));
+                match token {
+                    // `a..b` is `range(a, b)`, a following `step` gives its third argument
+                    TOKEN::RANGE(_) => stack.push(ASTNode::Function { op: TOKEN::FUNC("range".to_string(), 2, char_idx), args: vec![*right, *left] }),
+                    TOKEN::STEP(_) => match *right {
+                        ASTNode::Function { op: TOKEN::FUNC(name, 2, range_idx), mut args } if name == "range" => {
+                            args.push(*left);
+                            stack.push(ASTNode::Function { op: TOKEN::FUNC(name, 3, range_idx), args });
+                        },
+                        other => {
+                            errors.push(CalcError::UnexpectedToken(Span::at(char_idx)));
+                            stack.push(other);
+                        }
+                    },
+                    _ => stack.push(ASTNode::BOperator { op: token, left: right, right: left })
+                }
             },
             // left unary op
             TOKEN::FACT(char_idx) => {
                 match stack.pop(){
                     Some(v) =>  stack.push(ASTNode::ULOperator { op: token, left: Box::new(v) }),
-                    None => { return Err((ARG_MISS_ERROR, Some(char_idx))); }
+                    None => {
+                        errors.push(CalcError::MissingOperand(Span::at(char_idx)));
+                        stack.push(placeholder());
+                    }
                 };
             },
             // right unary op
-            TOKEN::SQRT(char_idx) | TOKEN::NOT(char_idx) => {
+            TOKEN::SQRT(char_idx) | TOKEN::NOT(char_idx) | TOKEN::NEG(char_idx) | TOKEN::BNOT(char_idx) => {
                 match stack.pop() {
                     Some(v) => { stack.push(ASTNode::UROperator { op: token, right: Box::new(v) }) },
-                    None => { return Err((ARG_MISS_ERROR, Some(char_idx))); }
+                    None => {
+                        errors.push(CalcError::MissingOperand(Span::at(char_idx)));
+                        stack.push(placeholder());
+                    }
                 };
             }
             TOKEN::NUMBER(num, _) => {
                 stack.push(ASTNode::Number(num));
             },
             TOKEN::EXPR(expr_root, _) => stack.push(ASTNode::Expression(expr_root)),
+            TOKEN::PARAM(name, _) => stack.push(ASTNode::Param(name)),
+            // The user-defined functions are checked when they are called, they may be redefined until then
+            TOKEN::FUNC(ref name, argc, char_idx) => {
+                if builtins::is_builtin(name) && !is_user_function(name, definition) && !builtins::accepts(name, argc) {
+                    errors.push(CalcError::Arity(Span::at(char_idx)));
+                    stack.truncate(stack.len().saturating_sub(argc));
+                    stack.push(placeholder());
+                    continue;
+                }
+                if stack.len() < argc {
+                    errors.push(CalcError::MissingOperand(Span::at(char_idx)));
+                    stack.resize_with(argc, placeholder);
+                }
+                let mut args = stack.split_off(stack.len() - argc);
+                if name == "if" {
+                    let (otherwise, then, cond) = (args.pop().unwrap(), args.pop().unwrap(), args.pop().unwrap());
+                    stack.push(ASTNode::Condition { cond: Box::new(cond), then: Box::new(then), otherwise: Box::new(otherwise) });
+                }else if (name == "sum" || name == "prod") && argc > 1 {
+                    if argc == 2 {
+                        errors.push(CalcError::Arity(Span::at(char_idx)));
+                        stack.push(placeholder());
+                        continue;
+                    }
+                    let (body, over, var) = (args.pop().unwrap(), args.pop().unwrap(), args.pop().unwrap());
+                    match var {
+                        ASTNode::Param(var) => stack.push(ASTNode::Reduce { op: token, var, over: Box::new(over), body: Box::new(body) }),
+                        _ => {
+                            errors.push(CalcError::BoundVariable(Span::at(char_idx)));
+                            stack.push(placeholder());
+                        }
+                    }
+                }else{
+                    stack.push(ASTNode::Function { op: token, args });
+                }
+            },
+            // The indexed value is the argument before the ones in the brackets
+            TOKEN::INDEX(name, argc, char_idx) => {
+                if !builtins::accepts(&name, argc+1) { errors.push(CalcError::Arity(Span::at(char_idx))); }
+                if stack.len() < argc+1 {
+                    errors.push(CalcError::MissingOperand(Span::at(char_idx)));
+                    stack.resize_with(argc+1, placeholder);
+                }
+                let args = stack.split_off(stack.len() - argc - 1);
+                stack.push(ASTNode::Function { op: TOKEN::FUNC(name, argc+1, char_idx), args });
+            },
+            TOKEN::TERNARY(char_idx) => {
+                if stack.len() < 3 {
+                    errors.push(CalcError::MissingOperand(Span::at(char_idx)));
+                    stack.resize_with(3, placeholder);
+                }
+                let (otherwise, then, cond) = (stack.pop().unwrap(), stack.pop().unwrap(), stack.pop().unwrap());
+                stack.push(ASTNode::Condition { cond: Box::new(cond), then: Box::new(then), otherwise: Box::new(otherwise) });
+            },
+            TOKEN::QUESTION(char_idx) => errors.push(CalcError::MissingBranch(Span::at(char_idx))),
+            TOKEN::COLON(char_idx) => errors.push(CalcError::UnexpectedToken(Span::at(char_idx))),
+            TOKEN::COMMA(char_idx) => errors.push(CalcError::UnexpectedToken(Span::at(char_idx))),
             TOKEN::LPAREN(_) => {},
             TOKEN::RPAREN(_) => {},
         }
     }
-    return Ok(match stack.pop(){
+    return match stack.pop(){
         Some(v) => v,
-        None => { return Err((NO_RESULT_ERROR, None)); }
-    });
+        None => {
+            // An empty line is only worth an error when nothing else is wrong with it
+            if errors.is_empty() { errors.push(CalcError::NoResult(Span::at(0))); }
+            placeholder()
+        }
+    };
+}
+
+// Stands for a part of the line which could not be read
+fn placeholder() -> ASTNode {
+    return ASTNode::Value(Value::List(Vec::new()));
 }
\ No newline at end of file
@@ -0,0 +1,42 @@
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+
+use crate::number::{CalcNumber, NumberMode};
+
+lazy_static! {
+    // The mathematical constants and the physical ones in SI units under the `const.` prefix
+    pub static ref CONSTANTS: HashMap<&'static str, f64> = {
+        let mut m = HashMap::new();
+        m.insert("pi", std::f64::consts::PI);
+        m.insert("e", std::f64::consts::E);
+        m.insert("tau", std::f64::consts::TAU);
+        m.insert("phi", 1.618_033_988_749_895);
+        m.insert("inf", f64::INFINITY);
+        m.insert("nan", f64::NAN);
+        // Speed of light, Avogadro, Planck, reduced Planck, standard gravity, gravitational, Boltzmann,
+        // elementary charge and electron mass
+        m.insert("const.c", 299_792_458.0);
+        m.insert("const.NA", 6.022_140_76e23);
+        m.insert("const.h", 6.626_070_15e-34);
+        m.insert("const.hbar", 1.054_571_817e-34);
+        m.insert("const.g", 9.806_65);
+        m.insert("const.G", 6.674_30e-11);
+        m.insert("const.k", 1.380_649e-23);
+        m.insert("const.qe", 1.602_176_634e-19);
+        m.insert("const.me", 9.109_383_701_5e-31);
+        return m;
+    };
+}
+
+pub fn is_constant(name: &str) -> bool {
+    return CONSTANTS.contains_key(name);
+}
+
+// The constants are floats in the integer modes, so `2 * pi` is not truncated
+pub fn get(name: &str, mode: NumberMode) -> Option<CalcNumber> {
+    let value = *CONSTANTS.get(name)?;
+    if mode.is_whole() || !value.is_finite() {
+        return Some(CalcNumber::Float(value));
+    }
+    return Some(CalcNumber::Float(value).convert(mode));
+}
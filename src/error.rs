@@ -0,0 +1,215 @@
+use std::fmt;
+
+use crate::calc;
+use crate::suggest;
+
+// Characters of the input an error belongs to, `end` is exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        return Span { start, end };
+    }
+
+    // A single character like an operator
+    pub fn at(char_idx: usize) -> Span {
+        return Span { start: char_idx, end: char_idx + 1 };
+    }
+
+    // The `^^^` line under the characters of the span, a span past the end of the line marks its last character
+    pub fn underline(&self, line: &str) -> String {
+        let len = line.chars().count();
+        let start = self.start.min(len.saturating_sub(1));
+        let end = self.end.min(len).max(start + 1);
+        return format!("{}{}", " ".repeat(start), "^".repeat(end - start));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalcError {
+    // Text which is not a number, an operator or a name
+    UnknownToken(String, Span),
+    // A known token in a place where it cannot be used, like a `,` outside of a call
+    UnexpectedToken(Span),
+    // The name, its span and the closest known name when there is one
+    UnknownVariable(String, Span, Option<String>),
+    UnknownFunction(String, Span, Option<String>),
+    // `(` or `[` without a closing pair
+    UnclosedParen(char, Span),
+    // `)` or `]` without an opening pair
+    UnopenedParen(char, Span),
+    // A closing one of the other kind like `(1]`, the span of the opening one and the closing one
+    MismatchedParen(char, Span, Span),
+    NoResult(Span),
+    MissingOperand(Span),
+    MissingBranch(Span),
+    BoundVariable(Span),
+    ReservedName(String, Span),
+    // The second one of a name which is twice in a parameter list like `f(x, x) = 1`
+    DuplicateParam(String, Span),
+    Constant(String, Span),
+    DivideByZero(Span),
+    NegativeArgument(Span),
+    NotInteger(Span),
+    TooLarge(Span),
+    Overflow(Span),
+    Unsupported(Span),
+    Arity(Span),
+    Domain(Span),
+    Recursion(Span),
+    NotNumber(Span),
+    NotFunction(Span),
+    NotList(Span),
+    Length(Span),
+    Index(Span),
+    Dimension(Span),
+    Singular(Span),
+}
+
+impl CalcError {
+    pub fn span(&self) -> Span {
+        return match self {
+            CalcError::UnknownToken(_, span) | CalcError::UnknownVariable(_, span, _) | CalcError::UnknownFunction(_, span, _) |
+            CalcError::ReservedName(_, span) | CalcError::DuplicateParam(_, span) | CalcError::Constant(_, span) | CalcError::UnclosedParen(_, span) |
+            CalcError::UnopenedParen(_, span) | CalcError::MismatchedParen(_, _, span) => *span,
+            CalcError::UnexpectedToken(span) | CalcError::NoResult(span) |
+            CalcError::MissingOperand(span) | CalcError::MissingBranch(span) | CalcError::BoundVariable(span) |
+            CalcError::DivideByZero(span) | CalcError::NegativeArgument(span) | CalcError::NotInteger(span) |
+            CalcError::TooLarge(span) | CalcError::Overflow(span) | CalcError::Unsupported(span) |
+            CalcError::Arity(span) | CalcError::Domain(span) | CalcError::Recursion(span) |
+            CalcError::NotNumber(span) | CalcError::NotFunction(span) | CalcError::NotList(span) |
+            CalcError::Length(span) | CalcError::Index(span) | CalcError::Dimension(span) |
+            CalcError::Singular(span) => *span,
+        };
+    }
+
+    // The same error pointing at another part of the input, like the call of a function which failed
+    pub fn with_span(mut self, span: Span) -> CalcError {
+        match &mut self {
+            CalcError::UnknownToken(_, old) | CalcError::UnknownVariable(_, old, _) | CalcError::UnknownFunction(_, old, _) |
+            CalcError::ReservedName(_, old) | CalcError::DuplicateParam(_, old) | CalcError::Constant(_, old) | CalcError::UnclosedParen(_, old) |
+            CalcError::UnopenedParen(_, old) | CalcError::MismatchedParen(_, _, old) => *old = span,
+            CalcError::UnexpectedToken(old) | CalcError::NoResult(old) |
+            CalcError::MissingOperand(old) | CalcError::MissingBranch(old) | CalcError::BoundVariable(old) |
+            CalcError::DivideByZero(old) | CalcError::NegativeArgument(old) | CalcError::NotInteger(old) |
+            CalcError::TooLarge(old) | CalcError::Overflow(old) | CalcError::Unsupported(old) |
+            CalcError::Arity(old) | CalcError::Domain(old) | CalcError::Recursion(old) |
+            CalcError::NotNumber(old) | CalcError::NotFunction(old) | CalcError::NotList(old) |
+            CalcError::Length(old) | CalcError::Index(old) | CalcError::Dimension(old) |
+            CalcError::Singular(old) => *old = span,
+        }
+        return self;
+    }
+
+    // Another part of the input which belongs to the error, like the opening parenthesis of a wrong closing one
+    pub fn related(&self) -> Option<(Span, &'static str)> {
+        return match self {
+            CalcError::MismatchedParen(_, open, _) => Some((*open, "opened here")),
+            _ => None
+        };
+    }
+
+    // A few words shown next to the underline
+    pub fn label(&self) -> &'static str {
+        return match self {
+            CalcError::UnknownToken(..) => "unknown token",
+            CalcError::UnexpectedToken(_) => "not expected here",
+            CalcError::UnknownVariable(..) => "not defined",
+            CalcError::UnknownFunction(..) => "not defined",
+            CalcError::UnclosedParen(..) => "never closed",
+            CalcError::UnopenedParen(..) => "never opened",
+            CalcError::MismatchedParen(..) => "does not match",
+            CalcError::NoResult(_) => "empty expression",
+            CalcError::MissingOperand(_) => "operand is missing",
+            CalcError::MissingBranch(_) => "condition without `:`",
+            CalcError::BoundVariable(_) => "not a variable name",
+            CalcError::ReservedName(..) => "built-in name",
+            CalcError::DuplicateParam(..) => "already a parameter",
+            CalcError::Constant(..) => "built-in constant",
+            CalcError::DivideByZero(_) => "division by zero",
+            CalcError::NegativeArgument(_) => "negative argument",
+            CalcError::NotInteger(_) => "not an integer",
+            CalcError::TooLarge(_) => "argument is too large",
+            CalcError::Overflow(_) => "overflows",
+            CalcError::Unsupported(_) => "not supported",
+            CalcError::Arity(_) => "wrong number of arguments",
+            CalcError::Domain(_) => "out of the domain",
+            CalcError::Recursion(_) => "too deep",
+            CalcError::NotNumber(_) => "expected a number",
+            CalcError::NotFunction(_) => "expected a function",
+            CalcError::NotList(_) => "expected a list",
+            CalcError::Length(_) => "lengths differ",
+            CalcError::Index(_) => "out of range",
+            CalcError::Dimension(_) => "dimensions do not fit",
+            CalcError::Singular(_) => "singular matrix",
+        };
+    }
+
+    // A hint how the input could be fixed, when there is an obvious one
+    pub fn help(&self) -> Option<String> {
+        return match self {
+            CalcError::UnknownToken(token, _) => match token.as_str() {
+                "=" => Some("did you mean `==`? A value is assigned like `x = 1` at the start of the line".to_string()),
+                // `!` is the factorial, the not of other languages is `~`
+                "!=" => Some("did you mean `~=`?".to_string()),
+                "=>" => Some("did you mean `>=`? A function value is written like `x -> x*2`".to_string()),
+                _ => {
+                    let operators: Vec<String> = calc::OPERATORS.iter().map(|op| op.to_string()).collect();
+                    suggest::closest_operator(token, &operators).map(|op| format!("did you mean `{}`?", op))
+                }
+            },
+            CalcError::Overflow(_) => Some("use `:overflow saturate` or a wider mode like `:mode big`".to_string()),
+            CalcError::Recursion(_) => Some("the limit can be raised like `:depth 5000`".to_string()),
+            CalcError::ReservedName(name, _) => Some(format!("`{}` is a built-in function, choose another name", name)),
+            CalcError::Constant(name, _) => Some(format!("`{}` is a built-in constant, choose another name", name)),
+            _ => None
+        };
+    }
+}
+
+impl fmt::Display for CalcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CalcError::UnknownToken(token, _) => write!(f, "{}: Token cannot be parsed", token),
+            CalcError::UnexpectedToken(_) => write!(f, "Token cannot be parsed"),
+            CalcError::UnknownVariable(name, _, None) => write!(f, "{}: Variable is not defined", name),
+            CalcError::UnknownVariable(name, _, Some(close)) => write!(f, "{}: Variable is not defined, did you mean `{}`?", name, close),
+            CalcError::UnknownFunction(name, _, None) => write!(f, "{}: Function is not defined", name),
+            CalcError::UnknownFunction(name, _, Some(close)) => write!(f, "{}: Function is not defined, did you mean `{}`?", name, close),
+            CalcError::UnclosedParen(open, _) => write!(f, "{}: Parenthesis is never closed", open),
+            CalcError::UnopenedParen(close, _) => write!(f, "{}: Parenthesis has no opening pair", close),
+            CalcError::MismatchedParen(close, open, _) => {
+                write!(f, "{}: Parenthesis does not close the {} at {}", close, if *close == ')' { '[' } else { '(' }, open.start)
+            },
+            CalcError::NoResult(_) => write!(f, "No Result"),
+            CalcError::MissingOperand(_) => write!(f, "Argumentum is missing"),
+            CalcError::MissingBranch(_) => write!(f, "Condition is missing its `:` branch"),
+            CalcError::BoundVariable(_) => write!(f, "Expected a variable name like `sum(i, 1..10, i**2)`"),
+            CalcError::ReservedName(name, _) => write!(f, "{}: Name is reserved for a built-in", name),
+            CalcError::DuplicateParam(name, _) => write!(f, "{}: Parameter is repeated", name),
+            CalcError::Constant(name, _) => write!(f, "{}: Name is a built-in constant, only a parameter like `f(e) = e*2` can shadow it", name),
+            CalcError::DivideByZero(_) => write!(f, "Divided by zero"),
+            CalcError::NegativeArgument(_) => write!(f, "Argumentum cannot be negative"),
+            CalcError::NotInteger(_) => write!(f, "Argumentum must be an integer"),
+            CalcError::TooLarge(_) => write!(f, "Argumentum is too large"),
+            CalcError::Overflow(_) => write!(f, "Integer overflow"),
+            CalcError::Unsupported(_) => write!(f, "Operation is not supported for this number"),
+            CalcError::Arity(_) => write!(f, "Wrong number of arguments"),
+            CalcError::Domain(_) => write!(f, "Argumentum is out of the domain"),
+            CalcError::Recursion(_) => write!(f, "Maximum recursion depth exceeded"),
+            CalcError::NotNumber(_) => write!(f, "Value is not a number"),
+            CalcError::NotFunction(_) => write!(f, "Value is not a function"),
+            CalcError::NotList(_) => write!(f, "Value is not a list"),
+            CalcError::Length(_) => write!(f, "Lists have different lengths"),
+            CalcError::Index(_) => write!(f, "Index is out of range"),
+            CalcError::Dimension(_) => write!(f, "Matrix dimensions do not fit"),
+            CalcError::Singular(_) => write!(f, "Matrix is singular"),
+        }
+    }
+}
+
+impl std::error::Error for CalcError {}
@@ -0,0 +1,227 @@
+use num_bigint::BigInt;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use crate::calc::Value;
+use crate::error::{CalcError, Span};
+use crate::number::CalcNumber;
+
+// Largest `r` of `nCr` and `nPr` and largest `n` of `fib`, the results would not fit in the memory anyway
+const MAX_TERMS: u64 = 100_000;
+const MAX_FIB: u64 = 10_000_000;
+
+// Steps of the Pollard rho search before a factorization is given up
+const MAX_RHO_STEPS: usize = 1 << 20;
+
+// The Miller-Rabin test with these bases is exact below 3.3e24, above it is a very strong probable prime test
+const BASES: [u32; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+// The arguments are whole numbers in every mode, the result keeps the mode of the first one
+pub fn call(name: &str, args: &[CalcNumber], char_idx: usize) -> Result<Value, CalcError> {
+    let mode = args[0].mode();
+    let ints = args.iter().map(|arg| arg.to_big_integer(char_idx)).collect::<Result<Vec<BigInt>, CalcError>>()?;
+    let res = match name {
+        "nCr" => choose(&ints[0], &ints[1], char_idx)?,
+        "nPr" => permutations(&ints[0], &ints[1], char_idx)?,
+        "is_prime" => { return Ok(Value::Number(CalcNumber::from_bool(is_prime(&ints[0]), mode))); },
+        "next_prime" => next_prime(&ints[0]),
+        "factorize" => {
+            let factors = factorize(&ints[0], char_idx)?;
+            let items = factors.into_iter().map(|p| CalcNumber::from_integer(p, mode, char_idx).map(Value::Number)).collect::<Result<Vec<Value>, CalcError>>()?;
+            return Ok(Value::List(items));
+        },
+        "totient" => totient(&ints[0], char_idx)?,
+        "modinv" => modinv(&ints[0], &ints[1], char_idx)?,
+        "modpow" => modpow(&ints[0], &ints[1], &ints[2], char_idx)?,
+        "fib" => fib(&ints[0], char_idx)?,
+        "isqrt" => {
+            if ints[0].is_negative() { return Err(CalcError::NegativeArgument(Span::at(char_idx))); }
+            ints[0].sqrt()
+        },
+        "iroot" => iroot(&ints[0], &ints[1], char_idx)?,
+        _ => unreachable!()
+    };
+    return Ok(Value::Number(CalcNumber::from_integer(res, mode, char_idx)?));
+}
+
+// Number of terms of a product like `n * (n-1) * ...`
+fn terms(r: &BigInt, char_idx: usize) -> Result<u64, CalcError> {
+    if r.is_negative() { return Err(CalcError::NegativeArgument(Span::at(char_idx))); }
+    return match r.to_u64() {
+        Some(r) if r <= MAX_TERMS => Ok(r),
+        _ => Err(CalcError::TooLarge(Span::at(char_idx)))
+    };
+}
+
+fn choose(n: &BigInt, r: &BigInt, char_idx: usize) -> Result<BigInt, CalcError> {
+    if n.is_negative() || r.is_negative() { return Err(CalcError::NegativeArgument(Span::at(char_idx))); }
+    if r > n { return Ok(BigInt::zero()); }
+    // `nCr == nC(n-r)`, the shorter product is used
+    let k = terms(&(n - r).min(r.clone()), char_idx)?;
+    let mut res = BigInt::one();
+    for i in 0..k {
+        res = res * (n - i) / (i + 1);
+    }
+    return Ok(res);
+}
+
+fn permutations(n: &BigInt, r: &BigInt, char_idx: usize) -> Result<BigInt, CalcError> {
+    if n.is_negative() || r.is_negative() { return Err(CalcError::NegativeArgument(Span::at(char_idx))); }
+    if r > n { return Ok(BigInt::zero()); }
+    let k = terms(r, char_idx)?;
+    return Ok((0..k).fold(BigInt::one(), |acc, i| acc * (n - i)));
+}
+
+pub fn is_prime(n: &BigInt) -> bool {
+    if n < &BigInt::from(2) { return false; }
+    for p in BASES {
+        if *n == BigInt::from(p) { return true; }
+        if (n % p).is_zero() { return false; }
+    }
+    let n_minus_one = n - 1u32;
+    let mut d = n_minus_one.clone();
+    let mut s = 0;
+    while (&d % 2u32).is_zero() {
+        d >>= 1;
+        s += 1;
+    }
+    'witness: for a in BASES {
+        let mut x = BigInt::from(a).modpow(&d, n);
+        if x.is_one() || x == n_minus_one { continue; }
+        for _ in 1..s {
+            x = &x * &x % n;
+            if x == n_minus_one { continue 'witness; }
+        }
+        return false;
+    }
+    return true;
+}
+
+// The smallest prime larger than `n`
+fn next_prime(n: &BigInt) -> BigInt {
+    let mut candidate = if n < &BigInt::from(2) { BigInt::from(2) } else { n + 1u32 };
+    while !is_prime(&candidate) {
+        candidate += 1u32;
+    }
+    return candidate;
+}
+
+// Prime factors in increasing order with their multiplicity, `factorize(12)` is `[2, 2, 3]`
+fn factorize(n: &BigInt, char_idx: usize) -> Result<Vec<BigInt>, CalcError> {
+    if n.is_negative() { return Err(CalcError::NegativeArgument(Span::at(char_idx))); }
+    if n.is_zero() { return Err(CalcError::Domain(Span::at(char_idx))); }
+    let mut n = n.clone();
+    let mut factors: Vec<BigInt> = Vec::new();
+    // The small factors are found by trial division, the rest by Pollard rho
+    let mut p = BigInt::from(2);
+    while p < BigInt::from(1000) && &p * &p <= n {
+        while (&n % &p).is_zero() {
+            n /= &p;
+            factors.push(p.clone());
+        }
+        p += 1u32;
+    }
+    let mut rest = vec![n];
+    while let Some(m) = rest.pop() {
+        if m.is_one() { continue; }
+        if is_prime(&m) {
+            factors.push(m);
+            continue;
+        }
+        let d = pollard_rho(&m, char_idx)?;
+        rest.push(&m / &d);
+        rest.push(d);
+    }
+    factors.sort();
+    return Ok(factors);
+}
+
+// A non trivial divisor of the composite `n`
+fn pollard_rho(n: &BigInt, char_idx: usize) -> Result<BigInt, CalcError> {
+    let mut steps = 0;
+    let mut c = BigInt::one();
+    loop {
+        let step = |x: &BigInt| (x * x + &c) % n;
+        let (mut x, mut y, mut d) = (BigInt::from(2), BigInt::from(2), BigInt::one());
+        while d.is_one() {
+            x = step(&x);
+            y = step(&step(&y));
+            d = gcd(&(&x - &y).abs(), n);
+            steps += 1;
+            if steps > MAX_RHO_STEPS { return Err(CalcError::TooLarge(Span::at(char_idx))); }
+        }
+        if &d != n { return Ok(d); }
+        c += 1u32;
+    }
+}
+
+fn gcd(a: &BigInt, b: &BigInt) -> BigInt {
+    let (mut a, mut b) = (a.clone(), b.clone());
+    while !b.is_zero() {
+        let r = &a % &b;
+        a = b;
+        b = r;
+    }
+    return a.abs();
+}
+
+// Number of the integers up to `n` which are coprime to it
+fn totient(n: &BigInt, char_idx: usize) -> Result<BigInt, CalcError> {
+    if !n.is_positive() { return Err(CalcError::Domain(Span::at(char_idx))); }
+    let mut factors = factorize(n, char_idx)?;
+    factors.dedup();
+    return Ok(factors.iter().fold(n.clone(), |acc, p| acc / p * (p - 1u32)));
+}
+
+// `x` where `a * x % m == 1`, it only exists when `a` and `m` are coprime
+fn modinv(a: &BigInt, m: &BigInt, char_idx: usize) -> Result<BigInt, CalcError> {
+    if m.is_zero() { return Err(CalcError::DivideByZero(Span::at(char_idx))); }
+    if m.is_negative() { return Err(CalcError::Domain(Span::at(char_idx))); }
+    // Extended Euclidean algorithm, `x` is the coefficient of `a`
+    let (mut r0, mut r1) = (a.clone() % m, m.clone());
+    let (mut x0, mut x1) = (BigInt::one(), BigInt::zero());
+    while !r1.is_zero() {
+        let q = &r0 / &r1;
+        (r0, r1) = (r1.clone(), &r0 - &q * &r1);
+        (x0, x1) = (x1.clone(), &x0 - &q * &x1);
+    }
+    if !r0.abs().is_one() { return Err(CalcError::Domain(Span::at(char_idx))); }
+    let x = x0 * r0.signum();
+    return Ok(((x % m) + m) % m);
+}
+
+// `b ** e % m` without the huge `b ** e`, a negative exponent uses the modular inverse
+fn modpow(b: &BigInt, e: &BigInt, m: &BigInt, char_idx: usize) -> Result<BigInt, CalcError> {
+    if m.is_zero() { return Err(CalcError::DivideByZero(Span::at(char_idx))); }
+    if m.is_negative() { return Err(CalcError::Domain(Span::at(char_idx))); }
+    if e.is_negative() {
+        return Ok(modinv(b, m, char_idx)?.modpow(&-e, m));
+    }
+    return Ok(b.modpow(e, m));
+}
+
+fn fib(n: &BigInt, char_idx: usize) -> Result<BigInt, CalcError> {
+    if n.is_negative() { return Err(CalcError::NegativeArgument(Span::at(char_idx))); }
+    return match n.to_u64() {
+        Some(n) if n <= MAX_FIB => Ok(fib_pair(n).0),
+        _ => Err(CalcError::TooLarge(Span::at(char_idx)))
+    };
+}
+
+// `(F(n), F(n+1))` by fast doubling
+fn fib_pair(n: u64) -> (BigInt, BigInt) {
+    if n == 0 { return (BigInt::zero(), BigInt::one()); }
+    let (a, b) = fib_pair(n / 2);
+    let c = &a * (&b * 2 - &a);
+    let d = &a * &a + &b * &b;
+    return if n.is_multiple_of(2) { (c, d) } else { (d.clone(), c + d) };
+}
+
+// The `k`th root rounded towards zero, an odd root of a negative number is negative
+fn iroot(n: &BigInt, k: &BigInt, char_idx: usize) -> Result<BigInt, CalcError> {
+    let k = match k.to_u32() {
+        Some(k) if k > 0 => k,
+        _ => { return Err(CalcError::Domain(Span::at(char_idx))); }
+    };
+    if n.is_negative() && k.is_multiple_of(2) { return Err(CalcError::NegativeArgument(Span::at(char_idx))); }
+    return Ok(n.nth_root(k));
+}
@@ -1,6 +1,17 @@
+#![allow(clippy::upper_case_acronyms, clippy::needless_return)]
+
 use std::io::prelude::*;
 use colored::*;
+mod builtins;
 mod calc;
+mod constants;
+mod error;
+mod integer;
+mod matrix;
+mod number;
+mod stats;
+mod suggest;
+mod trig;
 
 
 #[cfg(test)]
@@ -11,15 +22,23 @@ enum COMMAND {
     HELP,
     EMPTY,
     VARS,
+    FUNCS,
+    SET(String, String),
+    SETTINGS,
     EVAL
 }
 
 fn print_help(){
     println!("========= HELP =========");
-    println!("commands: \x1b[1;36m(quit, q)\x1b[0m");
+    println!("commands: \x1b[1;36m(quit, q)\x1b[0m, \x1b[1;36mvars\x1b[0m, \x1b[1;36mfuncs\x1b[0m");
+    println!("settings: \x1b[1;36m:mode (u8..u128 | i8..i128 | float | big | rational | complex)\x1b[0m, \x1b[1;36m:overflow (wrap | checked | saturate)\x1b[0m,");
+    println!("          \x1b[1;36m:display (fraction | decimal)\x1b[0m, \x1b[1;36m:base (dec | hex | oct | bin | all | 2..36)\x1b[0m,");
+    println!("          \x1b[1;36m:depth (1..10000)\x1b[0m, \x1b[1;36m:angle (rad | deg | grad)\x1b[0m, \x1b[1;36m:settings\x1b[0m");
+    println!("output:   \x1b[1;36m255 as hex\x1b[0m prints a single result in another base");
     println!("{:widthN$}|{:width$}", " Name", " Command", widthN=15, width=5);
     println!("{:widthN$}|{:width$}", " Add", "  +", widthN=15, width=5);
     println!("{:widthN$}|{:width$}", " Sub", "  -", widthN=15, width=5);
+    println!("{:widthN$}|{:width$}", " Negate", "  -", widthN=15, width=5);
     println!("{:widthN$}|{:width$}", " Mul", "  *", widthN=15, width=5);
     println!("{:widthN$}|{:width$}", " Div", "  /", widthN=15, width=5);
     println!("{:widthN$}|{:width$}", " Exp", "  **", widthN=15, width=5);
@@ -28,17 +47,38 @@ fn print_help(){
     println!("{:widthN$}|{:width$}", " Mod", "  %", widthN=15, width=5);
     println!("{:widthN$}|{:width$}", " Equals", "  ==", widthN=15, width=5);
     println!("{:widthN$}|{:width$}", " Not", "  ~", widthN=15, width=5);
-    println!("{:widthN$}|{:width$}", " Not Equal", "  n=", widthN=15, width=5);
+    println!("{:widthN$}|{:width$}", " Not Equal", "  ~=", widthN=15, width=5);
     println!("{:widthN$}|{:width$}", " Logic OR", "  ||", widthN=15, width=5);
     println!("{:widthN$}|{:width$}", " Bit OR", "  |", widthN=15, width=5);
     println!("{:widthN$}|{:width$}", " Logic AND", "  &&", widthN=15, width=5);
     println!("{:widthN$}|{:width$}", " Bit AND", "  &", widthN=15, width=5);
     println!("{:widthN$}|{:width$}", " Logic XOR", "  ^^", widthN=15, width=5);
     println!("{:widthN$}|{:width$}", " Bit XOR", "  ^", widthN=15, width=5);
+    println!("{:widthN$}|{:width$}", " Bit NOT", "  !x", widthN=15, width=5);
+    println!("{:widthN$}|{:width$}", " Shift Left", "  <<", widthN=15, width=5);
+    println!("{:widthN$}|{:width$}", " Shift Right", "  >>", widthN=15, width=5);
+    println!("{:widthN$}|{:width$}", " Rotate Left", "  <<<", widthN=15, width=5);
+    println!("{:widthN$}|{:width$}", " Rotate Right", "  >>>", widthN=15, width=5);
     println!("{:widthN$}|{:width$}", " Greater Than", "  >", widthN=15, width=5);
     println!("{:widthN$}|{:width$}", " Greater Equal", "  >=", widthN=15, width=5);
     println!("{:widthN$}|{:width$}", " Less Than", "  <", widthN=15, width=5);
     println!("{:widthN$}|{:width$}", " Less Equal", "  <=", widthN=15, width=5);
+    println!("{:widthN$}|{:width$}", " Concatenate", "  ++", widthN=15, width=5);
+    println!("{:widthN$}|{:width$}", " Matrix Mul", "  a@b", widthN=15, width=5);
+    println!("{:widthN$}|{:width$}", " Range", "  a..b", widthN=15, width=5);
+    println!("literals: \x1b[1;36m0xFF\x1b[0m, \x1b[1;36m0b1010\x1b[0m, \x1b[1;36m0o755\x1b[0m, \x1b[1;36m1_000_000\x1b[0m");
+    println!("lists: \x1b[1;36m[1, 2, 3]\x1b[0m, \x1b[1;36mv[0]\x1b[0m, \x1b[1;36mv[1:3]\x1b[0m, \x1b[1;36mlen(v)\x1b[0m, the operators work item by item like \x1b[1;36m[1, 2] * 2\x1b[0m");
+    println!("functions: \x1b[1;36mabs min max gcd lcm pow sqrt cbrt log log2 ln exp floor ceil round sign clamp\x1b[0m, like \x1b[1;36mmax(3, 9, 4)\x1b[0m");
+    println!("           \x1b[1;36msin cos tan asin acos atan atan2 sinh cosh tanh asinh acosh atanh hypot\x1b[0m,");
+    println!("           \x1b[1;36mnCr nPr is_prime next_prime factorize totient modinv modpow fib isqrt iroot\x1b[0m,");
+    println!("           \x1b[1;36mlist len at slice concat range sum prod map filter fold sum_over compose\x1b[0m,");
+    println!("           \x1b[1;36mmean median mode variance variance_s stddev stddev_s percentile count histogram correlation\x1b[0m,");
+    println!("           \x1b[1;36mtranspose det inv identity solve\x1b[0m, like \x1b[1;36mmap(x -> x*2, range(1, 5))\x1b[0m");
+    println!("           own functions are defined like \x1b[1;36mf(x, y) = x**2 + y\x1b[0m, they may call themselves");
+    println!("           function values are written like \x1b[1;36mx -> x*2\x1b[0m or \x1b[1;36m(a, b) -> a + b\x1b[0m");
+    println!("constants: \x1b[1;36mpi e tau phi inf nan\x1b[0m, physical ones like \x1b[1;36mconst.c const.NA const.h const.hbar const.g const.G const.k const.qe const.me\x1b[0m");
+    println!("ranges: \x1b[1;36m1..10\x1b[0m, \x1b[1;36m0..100 step 5\x1b[0m, \x1b[1;36msum(i, 1..100, i**2)\x1b[0m, \x1b[1;36mprod(k, 1..5, k)\x1b[0m");
+    println!("conditional: \x1b[1;36mcond ? a : b\x1b[0m or \x1b[1;36mif(cond, a, b)\x1b[0m");
     println!("evaluation:");
     println!("{}", "\t3*1-3+2 ~= @16/2!-(6&3) && @(2**3*50)>=19".green());
     println!("{}", "\t=> 1".green())
@@ -57,20 +97,39 @@ fn get_command(input: &str) -> COMMAND {
     if cmd=="quit" || cmd=="q" { return COMMAND::QUIT; }
     if cmd=="help" || cmd=="h" { return COMMAND::HELP; }
     if cmd=="vars" { return COMMAND::VARS; }
-    if cmd=="" { return COMMAND::EMPTY; }
+    if cmd=="funcs" { return COMMAND::FUNCS; }
+    if cmd==":settings" { return COMMAND::SETTINGS; }
+    if let Some(setting) = cmd.strip_prefix(':') {
+        let mut parts = setting.split_whitespace();
+        let name = parts.next().unwrap_or("").to_string();
+        let value = parts.collect::<Vec<&str>>().join(" ");
+        return COMMAND::SET(name, value);
+    }
+    if cmd.is_empty() { return COMMAND::EMPTY; }
     return COMMAND::EVAL;
 }
 
-fn print_error(msg: String, char_idx: Option<usize>) {
-    let maybe_char_idx: String = match char_idx {
-        Some(ci) => format!(" at {}", ci),
-        None => "".to_string(),
-    };
-    println!("{} {}{}", "Error:".red().bold(), msg.red(), maybe_char_idx.red());
+fn print_error(msg: String) {
+    println!("{} {}", "Error:".red().bold(), msg.red());
+}
+
+// Shows the input again with the part of the error underlined, like `1/0` with `^` under the `/`
+fn print_diagnostic(input: &str, err: &error::CalcError) {
+    print_error(err.to_string());
+    println!("{} {}", " |".blue().bold(), input);
+    println!("{} {} {}", " |".blue().bold(), err.span().underline(input).red().bold(), err.label().red().bold());
+    if let Some((span, label)) = err.related() {
+        println!("{} {} {}", " |".blue().bold(), span.underline(input).blue().bold(), label.blue().bold());
+    }
+    if let Some(help) = err.help() {
+        println!("{} {}", " = help:".blue().bold(), help);
+    }
 }
 
 fn print_result(msg: String) {
-    println!("{} {}", "=>".green(), msg.green().bold());
+    for (i, line) in msg.lines().enumerate() {
+        println!("{} {}", if i == 0 { "=>" } else { "  " }.green(), line.green().bold());
+    }
 }
 
 fn main() -> std::io::Result<()>{
@@ -80,13 +139,30 @@ fn main() -> std::io::Result<()>{
         let input: String = get_line().trim().to_string();
         match get_command(&input) {
             COMMAND::EVAL => {
-                match calc::eval(input) {
-                    Ok(res) => print_result(res.to_string()),
-                    Err((err, char_idx)) => print_error(err, char_idx)
+                let (expr, base) = calc::split_base_suffix(&input);
+                match calc::eval(expr.clone()) {
+                    Ok(res) => print_result(calc::format_result(&res, base)),
+                    Err(err) => {
+                        // Every mistake of the line is shown at once when it cannot be parsed
+                        let errors = calc::check(&expr);
+                        if errors.is_empty() {
+                            print_diagnostic(&expr, &err);
+                        }else{
+                            errors.iter().for_each(|err| print_diagnostic(&expr, err));
+                        }
+                    }
                 };
             },
             COMMAND::HELP => print_help(),
             COMMAND::VARS => calc::get_vars().into_iter().for_each(|line| println!("{}", line.blue().bold())),
+            COMMAND::FUNCS => calc::get_funcs().into_iter().for_each(|line| println!("{}", line.blue().bold())),
+            COMMAND::SET(name, value) => {
+                match calc::set_option(&name, &value) {
+                    Ok(msg) => println!("{}", msg.blue().bold()),
+                    Err(err) => print_error(err)
+                };
+            },
+            COMMAND::SETTINGS => calc::get_options().into_iter().for_each(|line| println!("{}", line.blue().bold())),
             COMMAND::EMPTY => { continue; }
             COMMAND::QUIT => { break; }
         }
@@ -0,0 +1,175 @@
+use num_bigint::BigInt;
+use num_traits::{One, Zero};
+use rayon::prelude::*;
+use std::cmp::Ordering;
+
+use crate::calc::Value;
+use crate::error::{CalcError, Span};
+use crate::number::{CalcNumber, NumberMode};
+
+// Rows of numbers, every row has the same length
+type Matrix = Vec<Vec<CalcNumber>>;
+
+// Largest identity matrix that is built
+const MAX_SIZE: usize = 1000;
+
+// Reads a list of lists like `[[1, 2], [3, 4]]`, a flat list like `[1, 2]` is a single row
+// the second value tells if it was a flat list
+fn to_matrix(val: &Value, char_idx: usize) -> Result<(Matrix, bool), CalcError> {
+    let items = val.list(char_idx)?;
+    let vector = items.iter().all(|item| matches!(item, Value::Number(_)));
+    let rows: Vec<&Value> = if vector { vec![val] } else { items.iter().collect() };
+    let mut matrix: Matrix = Vec::new();
+    for row in rows {
+        let row = row.list(char_idx)?.iter().map(|item| item.number(char_idx)).collect::<Result<Vec<CalcNumber>, CalcError>>()?;
+        if row.is_empty() || matrix.first().is_some_and(|first| first.len() != row.len()) { return Err(CalcError::Dimension(Span::at(char_idx))); }
+        matrix.push(row);
+    }
+    return Ok((matrix, vector));
+}
+
+fn to_value(matrix: Matrix) -> Value {
+    return Value::List(matrix.into_iter().map(|row| Value::List(row.into_iter().map(Value::Number).collect())).collect());
+}
+
+fn square(val: &Value, char_idx: usize) -> Result<Matrix, CalcError> {
+    let (matrix, _) = to_matrix(val, char_idx)?;
+    if matrix.len() != matrix[0].len() { return Err(CalcError::Dimension(Span::at(char_idx))); }
+    return Ok(matrix);
+}
+
+fn transposed(matrix: &Matrix) -> Matrix {
+    return (0..matrix[0].len()).map(|col| matrix.iter().map(|row| row[col].clone()).collect()).collect();
+}
+
+// A flat list is a row on the left and a column on the right, two of them give their dot product
+pub fn matmul(lval: &Value, rval: &Value, char_idx: usize) -> Result<Value, CalcError> {
+    let (left, left_vector) = to_matrix(lval, char_idx)?;
+    let (right, right_vector) = to_matrix(rval, char_idx)?;
+    let right = if right_vector { transposed(&right) } else { right };
+    if left[0].len() != right.len() { return Err(CalcError::Dimension(Span::at(char_idx))); }
+    let columns = transposed(&right);
+    let res = left.par_iter().map(|row| {
+        columns.iter().map(|col| {
+            let mut sum = row[0].mul(&col[0], char_idx)?;
+            for (l, r) in row.iter().zip(col).skip(1) {
+                sum = sum.add(&l.mul(r, char_idx)?, char_idx)?;
+            }
+            Ok(sum)
+        }).collect::<Result<Vec<CalcNumber>, CalcError>>()
+    }).collect::<Result<Matrix, CalcError>>()?;
+    return Ok(match (left_vector, right_vector) {
+        (true, true) => Value::Number(res[0][0].clone()),
+        (false, true) => Value::List(res.into_iter().map(|row| Value::Number(row[0].clone())).collect()),
+        (true, false) => to_value(res).list(char_idx)?[0].clone(),
+        (false, false) => to_value(res),
+    });
+}
+
+pub fn transpose(val: &Value, char_idx: usize) -> Result<Value, CalcError> {
+    let (matrix, _) = to_matrix(val, char_idx)?;
+    return Ok(to_value(transposed(&matrix)));
+}
+
+pub fn identity(size: &CalcNumber, mode: NumberMode, char_idx: usize) -> Result<Value, CalcError> {
+    let size = match size.to_big_integer(char_idx)? {
+        size if size > BigInt::zero() && size <= BigInt::from(MAX_SIZE) => usize::try_from(size).unwrap(),
+        _ => { return Err(CalcError::Dimension(Span::at(char_idx))); }
+    };
+    return Ok(to_value(unit(size, mode, char_idx)?));
+}
+
+fn unit(size: usize, mode: NumberMode, char_idx: usize) -> Result<Matrix, CalcError> {
+    let (zero, one) = (CalcNumber::from_integer(BigInt::zero(), mode, char_idx)?, CalcNumber::from_integer(BigInt::one(), mode, char_idx)?);
+    return Ok((0..size).map(|row| (0..size).map(|col| if row == col { one.clone() } else { zero.clone() }).collect()).collect());
+}
+
+// The integers are eliminated as fractions, so the results stay exact
+pub(crate) fn backend(mode: NumberMode) -> NumberMode {
+    return match mode {
+        NumberMode::Int(_) | NumberMode::Big => NumberMode::Rational,
+        mode => mode
+    };
+}
+
+pub fn det(val: &Value, char_idx: usize) -> Result<Value, CalcError> {
+    let matrix = square(val, char_idx)?;
+    let mode = matrix[0][0].mode();
+    let (det, _) = eliminate(matrix, Vec::new(), char_idx)?;
+    return Ok(Value::Number(match mode {
+        NumberMode::Int(_) | NumberMode::Big => CalcNumber::from_integer(det.to_big_integer(char_idx)?, mode, char_idx)?,
+        _ => det
+    }));
+}
+
+pub fn inverse(val: &Value, char_idx: usize) -> Result<Value, CalcError> {
+    let matrix = square(val, char_idx)?;
+    let unit = unit(matrix.len(), backend(matrix[0][0].mode()), char_idx)?;
+    return match eliminate(matrix, unit, char_idx)? {
+        (_, Some(inv)) => Ok(to_value(inv)),
+        (_, None) => Err(CalcError::Singular(Span::at(char_idx)))
+    };
+}
+
+// Solves `a @ x == b`, `b` is a vector or a matrix with a column for every system
+pub fn solve(a: &Value, b: &Value, char_idx: usize) -> Result<Value, CalcError> {
+    let matrix = square(a, char_idx)?;
+    let (rhs, vector) = to_matrix(b, char_idx)?;
+    let rhs = if vector { transposed(&rhs) } else { rhs };
+    if rhs.len() != matrix.len() { return Err(CalcError::Dimension(Span::at(char_idx))); }
+    return match eliminate(matrix, rhs, char_idx)? {
+        (_, Some(x)) if vector => Ok(Value::List(x.into_iter().map(|row| Value::Number(row[0].clone())).collect())),
+        (_, Some(x)) => Ok(to_value(x)),
+        (_, None) => Err(CalcError::Singular(Span::at(char_idx)))
+    };
+}
+
+// Gauss-Jordan elimination of `a` with the same row operations on `b`
+// gives the determinant of `a` and the reduced `b`, which is missing when `a` is singular
+fn eliminate(a: Matrix, b: Matrix, char_idx: usize) -> Result<(CalcNumber, Option<Matrix>), CalcError> {
+    let mode = backend(a[0][0].mode());
+    let convert = |m: Matrix| -> Matrix { m.into_iter().map(|row| row.into_iter().map(|v| v.convert(mode)).collect()).collect() };
+    let (mut a, mut b) = (convert(a), convert(b));
+    let mut det = CalcNumber::from_integer(BigInt::one(), mode, char_idx)?;
+    for col in 0..a.len() {
+        // The largest pivot keeps the float rounding errors small
+        let mut pivot_row: Option<usize> = None;
+        for row in col..a.len() {
+            if a[row][col].is_zero() { continue; }
+            let larger = match pivot_row {
+                Some(best) => a[row][col].abs(char_idx)?.compare(&a[best][col].abs(char_idx)?) == Some(Ordering::Greater),
+                None => true
+            };
+            if larger { pivot_row = Some(row); }
+        }
+        let pivot_row = match pivot_row {
+            Some(row) => row,
+            None => { return Ok((CalcNumber::from_integer(BigInt::zero(), mode, char_idx)?, None)); }
+        };
+        if pivot_row != col {
+            a.swap(pivot_row, col);
+            if !b.is_empty() { b.swap(pivot_row, col); }
+            det = det.neg(char_idx)?;
+        }
+        let pivot = a[col][col].clone();
+        det = det.mul(&pivot, char_idx)?;
+        a[col] = a[col].iter().map(|v| v.div(&pivot, char_idx)).collect::<Result<Vec<CalcNumber>, CalcError>>()?;
+        if !b.is_empty() {
+            b[col] = b[col].iter().map(|v| v.div(&pivot, char_idx)).collect::<Result<Vec<CalcNumber>, CalcError>>()?;
+        }
+        for row in 0..a.len() {
+            let factor = a[row][col].clone();
+            if row == col || factor.is_zero() { continue; }
+            a[row] = subtract_scaled(&a[row], &a[col], &factor, char_idx)?;
+            if !b.is_empty() {
+                b[row] = subtract_scaled(&b[row], &b[col], &factor, char_idx)?;
+            }
+        }
+    }
+    return Ok((det, Some(b)));
+}
+
+// `row - factor * pivot_row`
+fn subtract_scaled(row: &[CalcNumber], pivot_row: &[CalcNumber], factor: &CalcNumber, char_idx: usize) -> Result<Vec<CalcNumber>, CalcError> {
+    return row.iter().zip(pivot_row).map(|(v, p)| v.sub(&factor.mul(p, char_idx)?, char_idx)).collect();
+}
@@ -0,0 +1,984 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_rational::Ratio;
+use num_traits::{FromPrimitive, One, Pow, Signed, ToPrimitive, Zero};
+
+use crate::error::{CalcError, Span};
+
+// What happens when a fixed width integer result does not fit into its type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    Wrap,
+    Checked,
+    Saturate,
+}
+
+impl Overflow {
+    pub fn from_name(name: &str) -> Option<Overflow> {
+        match name {
+            "wrap" | "wrapping" => Some(Overflow::Wrap),
+            "checked" => Some(Overflow::Checked),
+            "saturate" | "saturating" => Some(Overflow::Saturate),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Overflow::Wrap => "wrap",
+            Overflow::Checked => "checked",
+            Overflow::Saturate => "saturate",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntType {
+    pub bits: u32,
+    pub signed: bool,
+    pub overflow: Overflow,
+}
+
+impl IntType {
+    pub fn new(bits: u32, signed: bool, overflow: Overflow) -> IntType {
+        return IntType { bits, signed, overflow };
+    }
+
+    pub fn from_name(name: &str) -> Option<IntType> {
+        let (signed, bits) = match name {
+            "unsigned" => (false, "32"),
+            "signed" => (true, "64"),
+            _ => match name.split_at_checked(1) {
+                Some(("u", bits)) => (false, bits),
+                Some(("i", bits)) => (true, bits),
+                _ => { return None; }
+            }
+        };
+        return match bits {
+            "8" | "16" | "32" | "64" | "128" => Some(IntType::new(bits.parse().unwrap(), signed, Overflow::Wrap)),
+            _ => None
+        };
+    }
+
+    pub fn name(&self) -> String {
+        return format!("{}{}", if self.signed { "i" } else { "u" }, self.bits);
+    }
+
+    fn min(&self) -> BigInt {
+        if !self.signed { return BigInt::zero(); }
+        return -(BigInt::one() << (self.bits - 1));
+    }
+
+    fn max(&self) -> BigInt {
+        if !self.signed { return (BigInt::one() << self.bits) - 1; }
+        return (BigInt::one() << (self.bits - 1)) - 1;
+    }
+
+    // Keeps only the low bits of the two's complement form, like an `as` cast
+    fn wrap(&self, value: &BigInt) -> FixedInt {
+        let mask: BigInt = (BigInt::one() << self.bits) - 1;
+        return FixedInt { raw: (value & &mask).to_u128().unwrap(), ty: *self };
+    }
+
+    // Fits an exact result into the type following the overflow policy
+    fn fit(&self, value: BigInt, char_idx: usize) -> Result<FixedInt, CalcError> {
+        if value < self.min() || value > self.max() {
+            return match self.overflow {
+                Overflow::Wrap => Ok(self.wrap(&value)),
+                Overflow::Checked => Err(CalcError::Overflow(Span::at(char_idx))),
+                Overflow::Saturate => Ok(self.wrap(&if value < self.min() { self.min() } else { self.max() })),
+            };
+        }
+        return Ok(self.wrap(&value));
+    }
+}
+
+// A fixed width integer, the bits are stored in two's complement form
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedInt {
+    raw: u128,
+    ty: IntType,
+}
+
+impl FixedInt {
+    fn value(&self) -> BigInt {
+        if !self.ty.signed { return BigInt::from(self.raw); }
+        let shift = 128 - self.ty.bits;
+        return BigInt::from(((self.raw << shift) as i128) >> shift);
+    }
+
+    fn pow(&self, exp: &BigInt, char_idx: usize) -> Result<FixedInt, CalcError> {
+        let base = self.value();
+        if exp.is_negative() || base.abs() <= BigInt::one() {
+            return self.ty.fit(big_pow(&base, exp, char_idx)?, char_idx);
+        }
+        // Anything above this exponent cannot fit into the type, so it is not computed exactly
+        if exp <= &BigInt::from(self.ty.bits) {
+            return self.ty.fit(big_pow(&base, exp, char_idx)?, char_idx);
+        }
+        if self.ty.overflow == Overflow::Wrap {
+            let modulus = BigInt::one() << self.ty.bits;
+            return Ok(self.ty.wrap(&BigInt::from(self.raw).modpow(exp, &modulus)));
+        }
+        let negative = base.is_negative() && (exp % 2u32).is_one();
+        return self.ty.fit(if negative { self.ty.min() - 1 } else { self.ty.max() + 1 }, char_idx);
+    }
+
+    fn mask(&self) -> u128 {
+        if self.ty.bits == 128 { return u128::MAX; }
+        return (1u128 << self.ty.bits) - 1;
+    }
+
+    // The shifted out bits are dropped, the signed types shift right arithmetically
+    fn shift(&self, amount: u64, left: bool) -> FixedInt {
+        let amount = amount.min(self.ty.bits as u64) as usize;
+        if left { return self.ty.wrap(&(self.value() << amount)); }
+        return self.ty.wrap(&(self.value() >> amount));
+    }
+
+    fn rotate(&self, amount: u64, left: bool) -> FixedInt {
+        let bits = self.ty.bits;
+        let amount = (amount % bits as u64) as u32;
+        let amount = if left { amount } else { (bits - amount) % bits };
+        if amount == 0 { return *self; }
+        let raw = ((self.raw << amount) | (self.raw >> (bits - amount))) & self.mask();
+        return FixedInt { raw, ty: self.ty };
+    }
+
+    fn factorial(&self, char_idx: usize) -> Result<FixedInt, CalcError> {
+        let n = self.value();
+        if n.is_negative() { return Err(CalcError::NegativeArgument(Span::at(char_idx))); }
+        // n! has at least n/2 factors of two, so from here on the low bits are all zero
+        if self.ty.overflow == Overflow::Wrap && n >= BigInt::from(2 * self.ty.bits + 2) {
+            return Ok(self.ty.wrap(&BigInt::zero()));
+        }
+        let max = self.ty.max();
+        let mut res = BigInt::one();
+        let mut i = BigInt::from(2);
+        while i <= n {
+            res *= &i;
+            if self.ty.overflow != Overflow::Wrap && res > max { break; }
+            i += 1;
+        }
+        return self.ty.fit(res, char_idx);
+    }
+}
+
+type BigRational = Ratio<BigInt>;
+
+// Number of fraction digits when a rational result is shown as a decimal
+const DECIMAL_DIGITS: usize = 20;
+
+// 170! is the largest factorial an f64 can hold
+const MAX_FLOAT_FACTORIAL: i64 = 170;
+
+// Decides how the number literals are read and which arithmetic is used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberMode {
+    Int(IntType),
+    Float,
+    Big,
+    Rational,
+    Complex,
+}
+
+impl NumberMode {
+    pub fn from_name(name: &str) -> Option<NumberMode> {
+        match name {
+            "float" | "f64" => Some(NumberMode::Float),
+            "big" | "bigint" => Some(NumberMode::Big),
+            "rational" | "fraction" => Some(NumberMode::Rational),
+            "complex" => Some(NumberMode::Complex),
+            _ => IntType::from_name(name).map(NumberMode::Int)
+        }
+    }
+
+    // The integer modes, their numbers have no fraction part
+    pub fn is_whole(&self) -> bool {
+        return matches!(self, NumberMode::Int(_) | NumberMode::Big);
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            NumberMode::Int(ty) => ty.name(),
+            NumberMode::Float => "float".to_string(),
+            NumberMode::Big => "big".to_string(),
+            NumberMode::Rational => "rational".to_string(),
+            NumberMode::Complex => "complex".to_string(),
+        }
+    }
+}
+
+// How the rational results are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RationalDisplay {
+    #[default]
+    Fraction,
+    Decimal,
+}
+
+impl RationalDisplay {
+    pub fn from_name(name: &str) -> Option<RationalDisplay> {
+        match name {
+            "fraction" => Some(RationalDisplay::Fraction),
+            "decimal" => Some(RationalDisplay::Decimal),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RationalDisplay::Fraction => "fraction",
+            RationalDisplay::Decimal => "decimal",
+        }
+    }
+}
+
+// Unit of the angles taken and returned by the trigonometric functions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AngleMode {
+    #[default]
+    Rad,
+    Deg,
+    Grad,
+}
+
+impl AngleMode {
+    pub fn from_name(name: &str) -> Option<AngleMode> {
+        match name {
+            "rad" | "radian" => Some(AngleMode::Rad),
+            "deg" | "degree" => Some(AngleMode::Deg),
+            "grad" | "gon" => Some(AngleMode::Grad),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AngleMode::Rad => "rad",
+            AngleMode::Deg => "deg",
+            AngleMode::Grad => "grad",
+        }
+    }
+
+    // Size of a full turn in this unit
+    pub fn turn(&self) -> f64 {
+        match self {
+            AngleMode::Rad => std::f64::consts::TAU,
+            AngleMode::Deg => 360.0,
+            AngleMode::Grad => 400.0,
+        }
+    }
+}
+
+// Which radix the results are printed in, `All` prints the common ones below each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputBase {
+    Radix(u32),
+    All,
+}
+
+impl OutputBase {
+    pub fn from_name(name: &str) -> Option<OutputBase> {
+        match name {
+            "dec" | "decimal" | "10" => Some(OutputBase::Radix(10)),
+            "hex" | "16" => Some(OutputBase::Radix(16)),
+            "bin" | "binary" | "2" => Some(OutputBase::Radix(2)),
+            "oct" | "octal" | "8" => Some(OutputBase::Radix(8)),
+            "all" => Some(OutputBase::All),
+            _ => match name.parse() {
+                Ok(radix) if (2..=36).contains(&radix) => Some(OutputBase::Radix(radix)),
+                _ => None
+            }
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            OutputBase::Radix(10) => "dec".to_string(),
+            OutputBase::Radix(16) => "hex".to_string(),
+            OutputBase::Radix(2) => "bin".to_string(),
+            OutputBase::Radix(8) => "oct".to_string(),
+            OutputBase::Radix(radix) => radix.to_string(),
+            OutputBase::All => "all".to_string(),
+        }
+    }
+}
+
+impl Default for OutputBase {
+    fn default() -> OutputBase {
+        return OutputBase::Radix(10);
+    }
+}
+
+impl Default for NumberMode {
+    fn default() -> NumberMode {
+        return NumberMode::Int(IntType::new(32, false, Overflow::Wrap));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalcNumber {
+    Int(FixedInt),
+    Float(f64),
+    Big(BigInt),
+    Rational(BigRational),
+    Complex(Complex64),
+}
+
+impl fmt::Display for CalcNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CalcNumber::Int(v) => write!(f, "{}", v.value()),
+            CalcNumber::Float(v) => write!(f, "{}", format_float(*v)),
+            CalcNumber::Big(v) => write!(f, "{}", v),
+            CalcNumber::Rational(v) => write!(f, "{}", v),
+            CalcNumber::Complex(v) => {
+                if v.im == 0.0 { return write!(f, "{}", format_float(v.re)); }
+                let sign = if v.im < 0.0 { "-" } else { "+" };
+                let im = if v.im.abs() == 1.0 { String::new() } else { format_float(v.im.abs()) };
+                if v.re == 0.0 { return write!(f, "{}{}i", if v.im < 0.0 { "-" } else { "" }, im); }
+                write!(f, "{}{}{}i", format_float(v.re), sign, im)
+            },
+        }
+    }
+}
+
+impl CalcNumber {
+    // Integer modes only accept plain digits, the float mode also accepts `3.14`, `.5` and `1e-9`
+    // `span` is the literal in the input, a literal out of the range of the fixed width integers follows the overflow policy
+    pub fn parse(digits: &str, mode: NumberMode, span: Span) -> Result<CalcNumber, CalcError> {
+        let num = match mode {
+            NumberMode::Int(ty) => match digits.parse() {
+                Ok(v) => Some(CalcNumber::Int(ty.fit(v, span.start).map_err(|err| err.with_span(span))?)),
+                Err(_) => None
+            },
+            NumberMode::Float => digits.parse().ok().map(CalcNumber::Float),
+            NumberMode::Big => digits.parse().ok().map(CalcNumber::Big),
+            NumberMode::Rational => parse_decimal(digits).map(CalcNumber::Rational),
+            NumberMode::Complex => match digits.strip_suffix('i') {
+                Some(im) => im.parse().ok().map(|v| CalcNumber::Complex(Complex64::new(0.0, v))),
+                None => digits.parse().ok().map(|v| CalcNumber::Complex(Complex64::new(v, 0.0))),
+            },
+        };
+        return num.ok_or_else(|| CalcError::UnknownToken(digits.to_string(), span));
+    }
+
+    // The magnitude of the smallest signed integer like the `128` of `-128` in i8 only fits with its minus
+    pub fn parse_min(digits: &str, mode: NumberMode) -> Option<CalcNumber> {
+        let NumberMode::Int(ty) = mode else { return None; };
+        let value = -digits.parse::<BigInt>().ok()?;
+        return (ty.signed && value == ty.min()).then(|| CalcNumber::Int(ty.wrap(&value)));
+    }
+
+    // Non-decimal literals describe a bit pattern, so `0xFF` is -1 in i8
+    // a literal with more bits than the type follows the overflow policy like the decimal ones
+    pub fn from_radix(literal: &str, radix: u32, mode: NumberMode, span: Span) -> Result<CalcNumber, CalcError> {
+        let digits: String = literal.chars().skip(2).filter(|c| *c != '_').collect();
+        let Some(value) = BigInt::parse_bytes(digits.as_bytes(), radix) else {
+            return Err(CalcError::UnknownToken(literal.to_string(), span));
+        };
+        match mode {
+            NumberMode::Int(ty) if (&value >> ty.bits).is_zero() => Ok(CalcNumber::Int(ty.wrap(&value))),
+            NumberMode::Int(ty) => Ok(CalcNumber::Int(ty.fit(value, span.start).map_err(|err| err.with_span(span))?)),
+            _ => Ok(CalcNumber::Big(value).convert(mode))
+        }
+    }
+
+    // Only integer values have a radix form, the fixed width integers show their bit pattern
+    // so `-1` in i8 is `0xFF`, every other number keeps its sign like `-0xFF`
+    pub fn to_radix_string(&self, radix: u32, grouped: bool) -> Option<String> {
+        let (value, width) = match self {
+            CalcNumber::Int(v) => (BigInt::from(v.raw), v.ty.bits),
+            CalcNumber::Float(v) if v.is_finite() && v.fract() == 0.0 => (BigInt::from_f64(*v)?, 0),
+            CalcNumber::Big(v) => (v.clone(), 0),
+            CalcNumber::Rational(v) if v.is_integer() => (v.to_integer(), 0),
+            CalcNumber::Complex(v) if v.im == 0.0 => { return CalcNumber::Float(v.re).to_radix_string(radix, grouped); },
+            _ => { return None; }
+        };
+        let mut digits = value.abs().to_str_radix(radix).to_uppercase();
+        let prefix = match radix {
+            16 => "0x",
+            8 => "0o",
+            2 => "0b",
+            _ => ""
+        };
+        if grouped && radix == 2 {
+            // Fixed width integers are padded to their full width, the rest to whole nibbles
+            let width = if width > 0 { width as usize } else { digits.len().div_ceil(4) * 4 };
+            digits = format!("{:0>width$}", digits, width = width);
+        }
+        if grouped && (radix == 2 || radix == 16) {
+            digits = group_digits(&digits, 4);
+        }
+        let sign = if value.is_negative() { "-" } else { "" };
+        return Some(format!("{}{}{}", sign, prefix, digits));
+    }
+
+    pub fn imaginary_unit() -> CalcNumber {
+        return CalcNumber::Complex(Complex64::i());
+    }
+
+    // Rationals are written out with `DECIMAL_DIGITS` fraction digits, everything else as usual
+    pub fn to_decimal_string(&self) -> String {
+        match self {
+            CalcNumber::Rational(v) => {
+                let scaled = (v * BigInt::from(10).pow(DECIMAL_DIGITS as u32)).round().to_integer();
+                let digits = format!("{:0>width$}", scaled.abs(), width = DECIMAL_DIGITS + 1);
+                let (int_part, frac_part) = digits.split_at(digits.len() - DECIMAL_DIGITS);
+                let frac_part = frac_part.trim_end_matches('0');
+                let sign = if scaled.is_negative() { "-" } else { "" };
+                if frac_part.is_empty() { return format!("{}{}", sign, int_part); }
+                format!("{}{}.{}", sign, int_part, frac_part)
+            },
+            _ => self.to_string()
+        }
+    }
+
+    pub fn from_bool(b: bool, mode: NumberMode) -> CalcNumber {
+        match mode {
+            NumberMode::Int(ty) => CalcNumber::Int(FixedInt { raw: b as u128, ty }),
+            NumberMode::Float => CalcNumber::Float(if b { 1.0 } else { 0.0 }),
+            NumberMode::Big => CalcNumber::Big(BigInt::from(b as u32)),
+            NumberMode::Rational => CalcNumber::Rational(BigRational::from_integer(BigInt::from(b as u32))),
+            NumberMode::Complex => CalcNumber::Complex(Complex64::new(if b { 1.0 } else { 0.0 }, 0.0)),
+        }
+    }
+
+    pub fn mode(&self) -> NumberMode {
+        match self {
+            CalcNumber::Int(v) => NumberMode::Int(v.ty),
+            CalcNumber::Float(_) => NumberMode::Float,
+            CalcNumber::Big(_) => NumberMode::Big,
+            CalcNumber::Rational(_) => NumberMode::Rational,
+            CalcNumber::Complex(_) => NumberMode::Complex,
+        }
+    }
+
+    // Used when a stored variable is read in a different mode than it was computed in
+    pub fn convert(&self, mode: NumberMode) -> CalcNumber {
+        if self.mode() == mode { return self.clone(); }
+        match (self, mode) {
+            (CalcNumber::Int(v), NumberMode::Int(ty)) => CalcNumber::Int(ty.wrap(&v.value())),
+            (CalcNumber::Int(v), NumberMode::Float) => CalcNumber::Float(v.value().to_f64().unwrap()),
+            (CalcNumber::Int(v), NumberMode::Big) => CalcNumber::Big(v.value()),
+            (CalcNumber::Float(v), NumberMode::Int(ty)) => CalcNumber::Int(ty.wrap(&BigInt::from_f64(v.trunc()).unwrap_or_default())),
+            (CalcNumber::Float(v), NumberMode::Big) => CalcNumber::Big(BigInt::from_f64(v.trunc()).unwrap_or_default()),
+            (CalcNumber::Big(v), NumberMode::Int(ty)) => CalcNumber::Int(ty.wrap(v)),
+            (CalcNumber::Big(v), NumberMode::Float) => CalcNumber::Float(v.to_f64().unwrap_or(f64::NAN)),
+            (CalcNumber::Int(v), NumberMode::Rational) => CalcNumber::Rational(BigRational::from_integer(v.value())),
+            (CalcNumber::Big(v), NumberMode::Rational) => CalcNumber::Rational(BigRational::from_integer(v.clone())),
+            // The shortest decimal form of the float is what the user most likely meant
+            (CalcNumber::Float(v), NumberMode::Rational) => CalcNumber::Rational(parse_decimal(&format!("{:e}", v)).unwrap_or_default()),
+            (CalcNumber::Rational(v), NumberMode::Int(ty)) => CalcNumber::Int(ty.wrap(&v.to_integer())),
+            (CalcNumber::Rational(v), NumberMode::Big) => CalcNumber::Big(v.to_integer()),
+            (CalcNumber::Rational(v), NumberMode::Float) => CalcNumber::Float(rational_to_f64(v)),
+            (CalcNumber::Complex(v), mode) => CalcNumber::Float(v.re).convert(mode),
+            (_, NumberMode::Complex) => match self.convert(NumberMode::Float) {
+                CalcNumber::Float(v) => CalcNumber::Complex(Complex64::new(v, 0.0)),
+                _ => unreachable!()
+            },
+            _ => self.clone()
+        }
+    }
+
+    // Every value other than zero is true, the negative ones too
+    pub fn to_bool(&self) -> bool {
+        return !self.is_zero();
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            CalcNumber::Int(v) => v.raw == 0,
+            CalcNumber::Float(v) => *v == 0.0,
+            CalcNumber::Big(v) => v.is_zero(),
+            CalcNumber::Rational(v) => v.is_zero(),
+            CalcNumber::Complex(v) => v.is_zero(),
+        }
+    }
+
+    // The bitwise operators and the factorial need a whole number even in float mode
+    fn to_integer(&self, char_idx: usize) -> Result<i64, CalcError> {
+        match self {
+            CalcNumber::Int(v) => v.value().to_i64().ok_or(CalcError::TooLarge(Span::at(char_idx))),
+            CalcNumber::Float(v) => {
+                if v.fract() != 0.0 || !v.is_finite() { return Err(CalcError::NotInteger(Span::at(char_idx))); }
+                Ok(*v as i64)
+            },
+            CalcNumber::Big(v) => v.to_i64().ok_or(CalcError::TooLarge(Span::at(char_idx))),
+            CalcNumber::Rational(v) => rational_to_integer(v, char_idx)?.to_i64().ok_or(CalcError::TooLarge(Span::at(char_idx))),
+            CalcNumber::Complex(v) => {
+                if v.im != 0.0 { return Err(CalcError::NotInteger(Span::at(char_idx))); }
+                CalcNumber::Float(v.re).to_integer(char_idx)
+            },
+        }
+    }
+
+    pub fn add(&self, rhs: &CalcNumber, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        match (self, rhs.convert(self.mode())) {
+            (CalcNumber::Int(l), CalcNumber::Int(r)) => Ok(CalcNumber::Int(l.ty.fit(l.value() + r.value(), char_idx)?)),
+            (CalcNumber::Float(l), CalcNumber::Float(r)) => Ok(CalcNumber::Float(*l + r)),
+            (CalcNumber::Big(l), CalcNumber::Big(r)) => Ok(CalcNumber::Big(l + r)),
+            (CalcNumber::Rational(l), CalcNumber::Rational(r)) => Ok(CalcNumber::Rational(l + r)),
+            (CalcNumber::Complex(l), CalcNumber::Complex(r)) => Ok(CalcNumber::Complex(*l + r)),
+            _ => unreachable!()
+        }
+    }
+
+    pub fn sub(&self, rhs: &CalcNumber, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        match (self, rhs.convert(self.mode())) {
+            (CalcNumber::Int(l), CalcNumber::Int(r)) => Ok(CalcNumber::Int(l.ty.fit(l.value() - r.value(), char_idx)?)),
+            (CalcNumber::Float(l), CalcNumber::Float(r)) => Ok(CalcNumber::Float(*l - r)),
+            (CalcNumber::Big(l), CalcNumber::Big(r)) => Ok(CalcNumber::Big(l - r)),
+            (CalcNumber::Rational(l), CalcNumber::Rational(r)) => Ok(CalcNumber::Rational(l - r)),
+            (CalcNumber::Complex(l), CalcNumber::Complex(r)) => Ok(CalcNumber::Complex(*l - r)),
+            _ => unreachable!()
+        }
+    }
+
+    pub fn mul(&self, rhs: &CalcNumber, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        match (self, rhs.convert(self.mode())) {
+            (CalcNumber::Int(l), CalcNumber::Int(r)) => Ok(CalcNumber::Int(l.ty.fit(l.value() * r.value(), char_idx)?)),
+            (CalcNumber::Float(l), CalcNumber::Float(r)) => Ok(CalcNumber::Float(*l * r)),
+            (CalcNumber::Big(l), CalcNumber::Big(r)) => Ok(CalcNumber::Big(l * r)),
+            (CalcNumber::Rational(l), CalcNumber::Rational(r)) => Ok(CalcNumber::Rational(l * r)),
+            (CalcNumber::Complex(l), CalcNumber::Complex(r)) => Ok(CalcNumber::Complex(*l * r)),
+            _ => unreachable!()
+        }
+    }
+
+    pub fn div(&self, rhs: &CalcNumber, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        if rhs.is_zero() { return Err(CalcError::DivideByZero(Span::at(char_idx))); }
+        match (self, rhs.convert(self.mode())) {
+            (CalcNumber::Int(l), CalcNumber::Int(r)) => Ok(CalcNumber::Int(l.ty.fit(l.value() / r.value(), char_idx)?)),
+            (CalcNumber::Float(l), CalcNumber::Float(r)) => Ok(CalcNumber::Float(*l / r)),
+            (CalcNumber::Big(l), CalcNumber::Big(r)) => Ok(CalcNumber::Big(l / r)),
+            (CalcNumber::Rational(l), CalcNumber::Rational(r)) => Ok(CalcNumber::Rational(l / r)),
+            (CalcNumber::Complex(l), CalcNumber::Complex(r)) => Ok(CalcNumber::Complex(*l / r)),
+            _ => unreachable!()
+        }
+    }
+
+    pub fn rem(&self, rhs: &CalcNumber, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        if rhs.is_zero() { return Err(CalcError::DivideByZero(Span::at(char_idx))); }
+        match (self, rhs.convert(self.mode())) {
+            (CalcNumber::Int(l), CalcNumber::Int(r)) => Ok(CalcNumber::Int(l.ty.fit(l.value() % r.value(), char_idx)?)),
+            (CalcNumber::Float(l), CalcNumber::Float(r)) => Ok(CalcNumber::Float(*l % r)),
+            (CalcNumber::Big(l), CalcNumber::Big(r)) => Ok(CalcNumber::Big(l % r)),
+            (CalcNumber::Rational(l), CalcNumber::Rational(r)) => Ok(CalcNumber::Rational(l % r)),
+            (CalcNumber::Complex(l), CalcNumber::Complex(r)) => {
+                if l.im != 0.0 || r.im != 0.0 { return Err(CalcError::Unsupported(Span::at(char_idx))); }
+                Ok(CalcNumber::Complex(Complex64::new(l.re % r.re, 0.0)))
+            },
+            _ => unreachable!()
+        }
+    }
+
+    pub fn pow(&self, rhs: &CalcNumber, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        match (self, rhs.convert(self.mode())) {
+            (CalcNumber::Int(l), CalcNumber::Int(r)) => Ok(CalcNumber::Int(l.pow(&r.value(), char_idx)?)),
+            (CalcNumber::Float(l), CalcNumber::Float(r)) => Ok(CalcNumber::Float(l.powf(r))),
+            (CalcNumber::Big(l), CalcNumber::Big(r)) => Ok(CalcNumber::Big(big_pow(l, &r, char_idx)?)),
+            (CalcNumber::Rational(l), CalcNumber::Rational(r)) => {
+                let exp = match rational_to_integer(&r, char_idx)?.to_i32() {
+                    Some(exp) => exp,
+                    None => { return Err(CalcError::TooLarge(Span::at(char_idx))); }
+                };
+                if l.is_zero() && exp < 0 { return Err(CalcError::DivideByZero(Span::at(char_idx))); }
+                Ok(CalcNumber::Rational(Pow::pow(l, exp)))
+            },
+            (CalcNumber::Complex(l), CalcNumber::Complex(r)) => {
+                // Whole exponents are multiplied out so that `i**2` is exactly -1
+                if r.im == 0.0 && r.re.fract() == 0.0 && r.re.abs() <= i32::MAX as f64 {
+                    return Ok(CalcNumber::Complex(l.powi(r.re as i32)));
+                }
+                if l.is_zero() { return Ok(CalcNumber::Complex(Complex64::zero())); }
+                Ok(CalcNumber::Complex(l.powc(r)))
+            },
+            _ => unreachable!()
+        }
+    }
+
+    pub fn neg(&self, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        match self {
+            CalcNumber::Int(v) => Ok(CalcNumber::Int(v.ty.fit(-v.value(), char_idx)?)),
+            CalcNumber::Float(v) => Ok(CalcNumber::Float(-*v)),
+            CalcNumber::Big(v) => Ok(CalcNumber::Big(-v)),
+            CalcNumber::Rational(v) => Ok(CalcNumber::Rational(-v)),
+            CalcNumber::Complex(v) => Ok(CalcNumber::Complex(-v)),
+        }
+    }
+
+    pub fn sqrt(&self, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        match self {
+            CalcNumber::Int(v) => {
+                let value = v.value();
+                if value.is_negative() { return Err(CalcError::NegativeArgument(Span::at(char_idx))); }
+                Ok(CalcNumber::Int(v.ty.fit(value.sqrt(), char_idx)?))
+            },
+            CalcNumber::Float(v) => {
+                if *v < 0.0 { return Err(CalcError::NegativeArgument(Span::at(char_idx))); }
+                Ok(CalcNumber::Float(v.sqrt()))
+            },
+            CalcNumber::Big(v) => {
+                if v.is_negative() { return Err(CalcError::NegativeArgument(Span::at(char_idx))); }
+                Ok(CalcNumber::Big(v.sqrt()))
+            },
+            CalcNumber::Rational(v) => {
+                if v.is_negative() { return Err(CalcError::NegativeArgument(Span::at(char_idx))); }
+                let (numer, denom) = (v.numer().sqrt(), v.denom().sqrt());
+                if &(&numer * &numer) == v.numer() && &(&denom * &denom) == v.denom() {
+                    return Ok(CalcNumber::Rational(BigRational::new(numer, denom)));
+                }
+                // Irrational roots are approximated with twice the displayed precision
+                let scale = BigInt::from(10).pow(2 * DECIMAL_DIGITS as u32);
+                let root = (v * &scale * &scale).to_integer().sqrt();
+                Ok(CalcNumber::Rational(BigRational::new(root, scale)))
+            },
+            CalcNumber::Complex(v) => {
+                // A negated real number has a -0 imaginary part, which would pick the other branch
+                let v = if v.im == 0.0 { Complex64::new(v.re, 0.0) } else { *v };
+                Ok(CalcNumber::Complex(v.sqrt()))
+            },
+        }
+    }
+
+    pub fn factorial(&self, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        match self {
+            CalcNumber::Int(v) => Ok(CalcNumber::Int(v.factorial(char_idx)?)),
+            CalcNumber::Float(v) => {
+                if *v < 0.0 { return Err(CalcError::NegativeArgument(Span::at(char_idx))); }
+                let n = self.to_integer(char_idx)?;
+                // Every larger factorial is past the largest f64, it is not worth the loop
+                if n > MAX_FLOAT_FACTORIAL { return Ok(CalcNumber::Float(f64::INFINITY)); }
+                Ok(CalcNumber::Float((2..=n).fold(1.0, |acc, i| acc * i as f64)))
+            },
+            CalcNumber::Big(v) => {
+                if v.is_negative() { return Err(CalcError::NegativeArgument(Span::at(char_idx))); }
+                let n = match v.to_u64() {
+                    Some(n) => n,
+                    None => { return Err(CalcError::TooLarge(Span::at(char_idx))); }
+                };
+                Ok(CalcNumber::Big((2..=n).fold(BigInt::from(1), |acc, i| acc * i)))
+            },
+            CalcNumber::Rational(v) => {
+                let n = CalcNumber::Big(rational_to_integer(v, char_idx)?).factorial(char_idx)?;
+                Ok(n.convert(NumberMode::Rational))
+            },
+            CalcNumber::Complex(v) => {
+                let n = CalcNumber::Float(v.re).factorial(char_idx)?;
+                if v.im != 0.0 { return Err(CalcError::NotInteger(Span::at(char_idx))); }
+                Ok(n.convert(NumberMode::Complex))
+            }
+        }
+    }
+
+    // Exact integer results are fitted into the fixed width integers following the overflow policy
+    pub fn from_integer(value: BigInt, mode: NumberMode, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        match mode {
+            NumberMode::Int(ty) => Ok(CalcNumber::Int(ty.fit(value, char_idx)?)),
+            _ => Ok(CalcNumber::Big(value).convert(mode))
+        }
+    }
+
+    // Results of the float functions, the integer modes truncate them like the `@` operator
+    fn from_real(value: f64, mode: NumberMode, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        if value.is_nan() || value == f64::NEG_INFINITY { return Err(CalcError::Domain(Span::at(char_idx))); }
+        match mode {
+            NumberMode::Float => Ok(CalcNumber::Float(value)),
+            NumberMode::Rational | NumberMode::Complex => {
+                if value.is_infinite() { return Err(CalcError::TooLarge(Span::at(char_idx))); }
+                Ok(CalcNumber::Float(value).convert(mode))
+            },
+            _ => match BigInt::from_f64(value.trunc()) {
+                Some(value) => CalcNumber::from_integer(value, mode, char_idx),
+                None => Err(CalcError::TooLarge(Span::at(char_idx)))
+            }
+        }
+    }
+
+    pub fn abs(&self, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        match self {
+            CalcNumber::Int(v) => Ok(CalcNumber::Int(v.ty.fit(v.value().abs(), char_idx)?)),
+            CalcNumber::Float(v) => Ok(CalcNumber::Float(v.abs())),
+            CalcNumber::Big(v) => Ok(CalcNumber::Big(v.abs())),
+            CalcNumber::Rational(v) => Ok(CalcNumber::Rational(v.abs())),
+            CalcNumber::Complex(v) => Ok(CalcNumber::Complex(Complex64::new(v.norm(), 0.0))),
+        }
+    }
+
+    // -1, 0 or 1, a complex number is scaled onto the unit circle
+    pub fn signum(&self, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        match self {
+            CalcNumber::Int(v) => CalcNumber::from_integer(v.value().signum(), self.mode(), char_idx),
+            CalcNumber::Float(v) => Ok(CalcNumber::Float(if *v == 0.0 { 0.0 } else { v.signum() })),
+            CalcNumber::Big(v) => Ok(CalcNumber::Big(v.signum())),
+            CalcNumber::Rational(v) => Ok(CalcNumber::Rational(v.signum())),
+            CalcNumber::Complex(v) => Ok(CalcNumber::Complex(if v.is_zero() { *v } else { v / v.norm() })),
+        }
+    }
+
+    pub fn floor(&self) -> CalcNumber {
+        return self.round_with(f64::floor, BigRational::floor);
+    }
+
+    pub fn ceil(&self) -> CalcNumber {
+        return self.round_with(f64::ceil, BigRational::ceil);
+    }
+
+    // Halves are rounded away from zero
+    pub fn round(&self) -> CalcNumber {
+        return self.round_with(f64::round, BigRational::round);
+    }
+
+    fn round_with(&self, real: fn(f64) -> f64, rational: fn(&BigRational) -> BigRational) -> CalcNumber {
+        match self {
+            CalcNumber::Float(v) => CalcNumber::Float(real(*v)),
+            CalcNumber::Rational(v) => CalcNumber::Rational(rational(v)),
+            CalcNumber::Complex(v) => CalcNumber::Complex(Complex64::new(real(v.re), real(v.im))),
+            _ => self.clone()
+        }
+    }
+
+    pub fn cbrt(&self, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        match self {
+            CalcNumber::Int(v) => CalcNumber::from_integer(v.value().cbrt(), self.mode(), char_idx),
+            CalcNumber::Big(v) => Ok(CalcNumber::Big(v.cbrt())),
+            CalcNumber::Rational(v) => {
+                let (numer, denom) = (v.numer().cbrt(), v.denom().cbrt());
+                if &Pow::pow(&numer, 3u32) == v.numer() && &Pow::pow(&denom, 3u32) == v.denom() {
+                    return Ok(CalcNumber::Rational(BigRational::new(numer, denom)));
+                }
+                CalcNumber::from_real(rational_to_f64(v).cbrt(), self.mode(), char_idx)
+            },
+            CalcNumber::Float(v) => Ok(CalcNumber::Float(v.cbrt())),
+            CalcNumber::Complex(v) => Ok(CalcNumber::Complex(v.cbrt())),
+        }
+    }
+
+    pub fn ln(&self, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        return self.real_fn(f64::ln, Complex64::ln, char_idx);
+    }
+
+    pub fn exp(&self, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        return self.real_fn(f64::exp, Complex64::exp, char_idx);
+    }
+
+    // The integer modes give the exact floor of the logarithm, so `log(1000)` is never 2.999..
+    pub fn log(&self, base: &CalcNumber, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        match (self, base.convert(self.mode())) {
+            (CalcNumber::Int(_), b) | (CalcNumber::Big(_), b) => {
+                let (value, base) = (self.to_big_integer(char_idx)?, b.to_big_integer(char_idx)?);
+                if value.is_negative() { return Err(CalcError::NegativeArgument(Span::at(char_idx))); }
+                if value.is_zero() || base <= BigInt::one() { return Err(CalcError::Domain(Span::at(char_idx))); }
+                let mut res = BigInt::zero();
+                let mut power = base.clone();
+                while power <= value {
+                    power *= &base;
+                    res += 1;
+                }
+                CalcNumber::from_integer(res, self.mode(), char_idx)
+            },
+            (CalcNumber::Complex(v), CalcNumber::Complex(b)) => {
+                if v.is_zero() || b.is_zero() || b.is_one() { return Err(CalcError::Domain(Span::at(char_idx))); }
+                Ok(CalcNumber::Complex(v.ln() / b.ln()))
+            },
+            (_, b) => {
+                let (value, base) = (self.convert(NumberMode::Float), b.convert(NumberMode::Float));
+                let (CalcNumber::Float(value), CalcNumber::Float(base)) = (value, base) else { unreachable!() };
+                if value < 0.0 { return Err(CalcError::NegativeArgument(Span::at(char_idx))); }
+                if base <= 0.0 || base == 1.0 { return Err(CalcError::Domain(Span::at(char_idx))); }
+                let res = match base {
+                    2.0 => value.log2(),
+                    10.0 => value.log10(),
+                    _ => value.ln() / base.ln()
+                };
+                CalcNumber::from_real(res, self.mode(), char_idx)
+            }
+        }
+    }
+
+    fn real_fn(&self, real: fn(f64) -> f64, complex: fn(Complex64) -> Complex64, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        match self {
+            CalcNumber::Complex(v) => Ok(CalcNumber::Complex(complex(*v))),
+            _ => match self.convert(NumberMode::Float) {
+                CalcNumber::Float(v) => CalcNumber::from_real(real(v), self.mode(), char_idx),
+                _ => unreachable!()
+            }
+        }
+    }
+
+    pub fn bit_and(&self, rhs: &CalcNumber, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        match (self, rhs.convert(self.mode())) {
+            (CalcNumber::Int(l), CalcNumber::Int(r)) => Ok(CalcNumber::Int(FixedInt { raw: l.raw & r.raw, ty: l.ty })),
+            (CalcNumber::Float(_), r) => Ok(CalcNumber::Float((self.to_integer(char_idx)? & r.to_integer(char_idx)?) as f64)),
+            (CalcNumber::Complex(_), r) => Ok(CalcNumber::Complex(Complex64::new((self.to_integer(char_idx)? & r.to_integer(char_idx)?) as f64, 0.0))),
+            (CalcNumber::Big(l), CalcNumber::Big(r)) => Ok(CalcNumber::Big(l & r)),
+            (CalcNumber::Rational(l), CalcNumber::Rational(r)) => Ok(CalcNumber::Rational(BigRational::from_integer(rational_to_integer(l, char_idx)? & rational_to_integer(&r, char_idx)?))),
+            _ => unreachable!()
+        }
+    }
+
+    pub fn bit_or(&self, rhs: &CalcNumber, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        match (self, rhs.convert(self.mode())) {
+            (CalcNumber::Int(l), CalcNumber::Int(r)) => Ok(CalcNumber::Int(FixedInt { raw: l.raw | r.raw, ty: l.ty })),
+            (CalcNumber::Float(_), r) => Ok(CalcNumber::Float((self.to_integer(char_idx)? | r.to_integer(char_idx)?) as f64)),
+            (CalcNumber::Complex(_), r) => Ok(CalcNumber::Complex(Complex64::new((self.to_integer(char_idx)? | r.to_integer(char_idx)?) as f64, 0.0))),
+            (CalcNumber::Big(l), CalcNumber::Big(r)) => Ok(CalcNumber::Big(l | r)),
+            (CalcNumber::Rational(l), CalcNumber::Rational(r)) => Ok(CalcNumber::Rational(BigRational::from_integer(rational_to_integer(l, char_idx)? | rational_to_integer(&r, char_idx)?))),
+            _ => unreachable!()
+        }
+    }
+
+    pub fn bit_xor(&self, rhs: &CalcNumber, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        match (self, rhs.convert(self.mode())) {
+            (CalcNumber::Int(l), CalcNumber::Int(r)) => Ok(CalcNumber::Int(FixedInt { raw: l.raw ^ r.raw, ty: l.ty })),
+            (CalcNumber::Float(_), r) => Ok(CalcNumber::Float((self.to_integer(char_idx)? ^ r.to_integer(char_idx)?) as f64)),
+            (CalcNumber::Complex(_), r) => Ok(CalcNumber::Complex(Complex64::new((self.to_integer(char_idx)? ^ r.to_integer(char_idx)?) as f64, 0.0))),
+            (CalcNumber::Big(l), CalcNumber::Big(r)) => Ok(CalcNumber::Big(l ^ r)),
+            (CalcNumber::Rational(l), CalcNumber::Rational(r)) => Ok(CalcNumber::Rational(BigRational::from_integer(rational_to_integer(l, char_idx)? ^ rational_to_integer(&r, char_idx)?))),
+            _ => unreachable!()
+        }
+    }
+
+    pub fn shl(&self, rhs: &CalcNumber, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        return self.shift(rhs, true, char_idx);
+    }
+
+    pub fn shr(&self, rhs: &CalcNumber, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        return self.shift(rhs, false, char_idx);
+    }
+
+    fn shift(&self, rhs: &CalcNumber, left: bool, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        let amount = rhs.shift_amount(char_idx)?;
+        if let CalcNumber::Int(v) = self { return Ok(CalcNumber::Int(v.shift(amount, left))); }
+        let value = self.to_big_integer(char_idx)?;
+        let amount = match usize::try_from(amount) {
+            Ok(amount) if amount <= u32::MAX as usize => amount,
+            _ => { return Err(CalcError::TooLarge(Span::at(char_idx))); }
+        };
+        let res = if left { value << amount } else { value >> amount };
+        return Ok(CalcNumber::Big(res).convert(self.mode()));
+    }
+
+    // Rotation needs a width, so it only works on the fixed width integers
+    pub fn rotl(&self, rhs: &CalcNumber, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        match self {
+            CalcNumber::Int(v) => Ok(CalcNumber::Int(v.rotate(rhs.shift_amount(char_idx)?, true))),
+            _ => Err(CalcError::Unsupported(Span::at(char_idx)))
+        }
+    }
+
+    pub fn rotr(&self, rhs: &CalcNumber, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        match self {
+            CalcNumber::Int(v) => Ok(CalcNumber::Int(v.rotate(rhs.shift_amount(char_idx)?, false))),
+            _ => Err(CalcError::Unsupported(Span::at(char_idx)))
+        }
+    }
+
+    // Flips every bit of the fixed width integers, otherwise it is `-x - 1` like on an infinite two's complement
+    pub fn bit_not(&self, char_idx: usize) -> Result<CalcNumber, CalcError> {
+        match self {
+            CalcNumber::Int(v) => Ok(CalcNumber::Int(FixedInt { raw: v.raw ^ v.mask(), ty: v.ty })),
+            _ => Ok(CalcNumber::Big(!self.to_big_integer(char_idx)?).convert(self.mode()))
+        }
+    }
+
+    fn shift_amount(&self, char_idx: usize) -> Result<u64, CalcError> {
+        let amount = self.to_big_integer(char_idx)?;
+        if amount.is_negative() { return Err(CalcError::NegativeArgument(Span::at(char_idx))); }
+        return Ok(amount.to_u64().unwrap_or(u64::MAX));
+    }
+
+    pub fn to_big_integer(&self, char_idx: usize) -> Result<BigInt, CalcError> {
+        match self {
+            CalcNumber::Int(v) => Ok(v.value()),
+            CalcNumber::Float(v) => {
+                if v.fract() != 0.0 || !v.is_finite() { return Err(CalcError::NotInteger(Span::at(char_idx))); }
+                Ok(BigInt::from_f64(*v).unwrap())
+            },
+            CalcNumber::Big(v) => Ok(v.clone()),
+            CalcNumber::Rational(v) => rational_to_integer(v, char_idx),
+            CalcNumber::Complex(v) => {
+                if v.im != 0.0 { return Err(CalcError::NotInteger(Span::at(char_idx))); }
+                CalcNumber::Float(v.re).to_big_integer(char_idx)
+            },
+        }
+    }
+
+    // None when the numbers cannot be ordered (NaN in float mode)
+    pub fn compare(&self, rhs: &CalcNumber) -> Option<Ordering> {
+        match (self, rhs.convert(self.mode())) {
+            (CalcNumber::Int(l), CalcNumber::Int(r)) => Some(l.value().cmp(&r.value())),
+            (CalcNumber::Float(l), CalcNumber::Float(r)) => l.partial_cmp(&r),
+            (CalcNumber::Big(l), CalcNumber::Big(r)) => Some(l.cmp(&r)),
+            (CalcNumber::Rational(l), CalcNumber::Rational(r)) => Some(l.cmp(&r)),
+            (CalcNumber::Complex(l), CalcNumber::Complex(r)) => {
+                if *l == r { return Some(Ordering::Equal); }
+                // Only the numbers on the real line can be ordered
+                if l.im != 0.0 || r.im != 0.0 { return None; }
+                l.re.partial_cmp(&r.re)
+            },
+            _ => unreachable!()
+        }
+    }
+}
+
+fn big_pow(base: &BigInt, exp: &BigInt, char_idx: usize) -> Result<BigInt, CalcError> {
+    if exp.is_negative() {
+        // Only 1 and -1 have an integer reciprocal
+        if base.is_one() { return Ok(BigInt::one()); }
+        if (-base).is_one() { return Ok(if (exp % 2u32).is_zero() { BigInt::one() } else { -BigInt::one() }); }
+        return Ok(BigInt::zero());
+    }
+    return match exp.to_u32() {
+        Some(exp) => Ok(base.pow(exp)),
+        None => Err(CalcError::TooLarge(Span::at(char_idx)))
+    };
+}
+
+// Very large and very small values are easier to read in scientific notation
+fn format_float(v: f64) -> String {
+    if v.is_finite() && v != 0.0 && (v.abs() >= 1e16 || v.abs() < 1e-6) {
+        return format!("{:e}", v);
+    }
+    return format!("{}", v);
+}
+
+// Separates the digits into groups from the right with `_`, so the output can be read back
+fn group_digits(digits: &str, size: usize) -> String {
+    let mut res = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(size) { res.push('_'); }
+        res.push(c);
+    }
+    return res;
+}
+
+fn rational_to_integer(v: &BigRational, char_idx: usize) -> Result<BigInt, CalcError> {
+    if !v.is_integer() { return Err(CalcError::NotInteger(Span::at(char_idx))); }
+    return Ok(v.to_integer());
+}
+
+fn rational_to_f64(v: &BigRational) -> f64 {
+    match (v.numer().to_f64(), v.denom().to_f64()) {
+        (Some(numer), Some(denom)) if numer.is_finite() && denom.is_finite() => numer / denom,
+        _ => CalcNumber::Rational(v.clone()).to_decimal_string().parse().unwrap_or(f64::NAN)
+    }
+}
+
+// Reads a decimal literal like `3.14`, `.5` or `6.02E23` without losing precision
+fn parse_decimal(digits: &str) -> Option<BigRational> {
+    let (mantissa, exp) = match digits.find(['e', 'E']) {
+        Some(pos) => (&digits[..pos], digits[pos+1..].parse::<i32>().ok()?),
+        None => (digits, 0)
+    };
+    let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let numer: BigInt = format!("{}{}", int_part, frac_part).parse().ok()?;
+    let exp = exp - frac_part.len() as i32;
+    let ten = BigRational::from_integer(BigInt::from(10));
+    return Some(BigRational::from_integer(numer) * Pow::pow(&ten, exp));
+}
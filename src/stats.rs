@@ -0,0 +1,177 @@
+use num_bigint::BigInt;
+use num_traits::Zero;
+use rayon::prelude::*;
+use std::cmp::Ordering;
+
+use crate::calc::Value;
+use crate::error::{CalcError, Span};
+use crate::matrix;
+use crate::number::{CalcNumber, NumberMode};
+
+// Most bins a histogram may have
+const MAX_BINS: usize = 1000;
+
+// The items of a statistics argument, the integers are taken as fractions so the mean of `[1, 2]` is `3/2`
+// the second value is the mode of the items
+fn numbers(val: &Value, char_idx: usize) -> Result<(Vec<CalcNumber>, NumberMode), CalcError> {
+    let items = val.list(char_idx)?;
+    if items.is_empty() { return Err(CalcError::Domain(Span::at(char_idx))); }
+    let nums = items.iter().map(|item| item.number(char_idx)).collect::<Result<Vec<CalcNumber>, CalcError>>()?;
+    let mode = nums[0].mode();
+    return Ok((nums.into_iter().map(|num| num.convert(matrix::backend(mode))).collect(), mode));
+}
+
+// The items in increasing order, the numbers which cannot be ordered are rejected
+fn sorted(val: &Value, char_idx: usize) -> Result<(Vec<CalcNumber>, NumberMode), CalcError> {
+    let (mut nums, mode) = numbers(val, char_idx)?;
+    if nums.iter().any(|num| num.compare(num).is_none()) { return Err(CalcError::Unsupported(Span::at(char_idx))); }
+    nums.par_sort_by(|a, b| a.compare(b).unwrap());
+    return Ok((nums, mode));
+}
+
+// A whole result goes back to the integer mode of the items, like the mean of `[3, 9, 12]`
+fn narrow(num: CalcNumber, mode: NumberMode, char_idx: usize) -> Result<Value, CalcError> {
+    let whole = num.floor().compare(&num) == Some(Ordering::Equal);
+    return Ok(Value::Number(match mode {
+        NumberMode::Int(_) | NumberMode::Big if whole => CalcNumber::from_integer(num.to_big_integer(char_idx)?, mode, char_idx)?,
+        _ => num
+    }));
+}
+
+// The root of a fraction made from integers is a float when it is not exact
+fn root(num: &CalcNumber, mode: NumberMode, char_idx: usize) -> Result<CalcNumber, CalcError> {
+    let res = num.sqrt(char_idx)?;
+    if mode != num.mode() && res.mul(&res, char_idx)?.compare(num) != Some(Ordering::Equal) {
+        return num.convert(NumberMode::Float).sqrt(char_idx);
+    }
+    return Ok(res);
+}
+
+fn count(len: usize, mode: NumberMode, char_idx: usize) -> Result<CalcNumber, CalcError> {
+    return CalcNumber::from_integer(BigInt::from(len), mode, char_idx);
+}
+
+fn total(nums: &[CalcNumber], char_idx: usize) -> Result<CalcNumber, CalcError> {
+    let mut res = CalcNumber::from_integer(BigInt::zero(), nums[0].mode(), char_idx)?;
+    for num in nums {
+        res = res.add(num, char_idx)?;
+    }
+    return Ok(res);
+}
+
+fn average(nums: &[CalcNumber], char_idx: usize) -> Result<CalcNumber, CalcError> {
+    return total(nums, char_idx)?.div(&count(nums.len(), nums[0].mode(), char_idx)?, char_idx);
+}
+
+// The differences from the mean
+fn deviations(nums: &[CalcNumber], char_idx: usize) -> Result<Vec<CalcNumber>, CalcError> {
+    let mean = average(nums, char_idx)?;
+    return nums.iter().map(|num| num.sub(&mean, char_idx)).collect();
+}
+
+pub fn mean(val: &Value, char_idx: usize) -> Result<Value, CalcError> {
+    let (nums, mode) = numbers(val, char_idx)?;
+    return narrow(average(&nums, char_idx)?, mode, char_idx);
+}
+
+pub fn median(val: &Value, char_idx: usize) -> Result<Value, CalcError> {
+    let (nums, mode) = sorted(val, char_idx)?;
+    let mid = nums.len() / 2;
+    let res = if nums.len() % 2 == 1 { nums[mid].clone() } else { average(&nums[mid-1..=mid], char_idx)? };
+    return narrow(res, mode, char_idx);
+}
+
+// The most frequent item, the smallest one when more items are as frequent
+pub fn mode(val: &Value, char_idx: usize) -> Result<Value, CalcError> {
+    let (nums, mode) = sorted(val, char_idx)?;
+    let (mut best, mut best_len) = (0, 0);
+    let mut start = 0;
+    for k in 1..=nums.len() {
+        if k == nums.len() || nums[k].compare(&nums[start]) != Some(Ordering::Equal) {
+            if k - start > best_len {
+                (best, best_len) = (start, k - start);
+            }
+            start = k;
+        }
+    }
+    return narrow(nums[best].clone(), mode, char_idx);
+}
+
+// The population variance divides by the number of items, the sample variance by one less
+pub fn variance(val: &Value, sample: bool, char_idx: usize) -> Result<Value, CalcError> {
+    let (nums, mode) = numbers(val, char_idx)?;
+    return narrow(spread(&nums, sample, char_idx)?, mode, char_idx);
+}
+
+pub fn stddev(val: &Value, sample: bool, char_idx: usize) -> Result<Value, CalcError> {
+    let (nums, mode) = numbers(val, char_idx)?;
+    return narrow(root(&spread(&nums, sample, char_idx)?, mode, char_idx)?, mode, char_idx);
+}
+
+fn spread(nums: &[CalcNumber], sample: bool, char_idx: usize) -> Result<CalcNumber, CalcError> {
+    let len = if sample { nums.len() - 1 } else { nums.len() };
+    if len == 0 { return Err(CalcError::Domain(Span::at(char_idx))); }
+    let squares = deviations(nums, char_idx)?.iter().map(|d| d.mul(d, char_idx)).collect::<Result<Vec<CalcNumber>, CalcError>>()?;
+    return total(&squares, char_idx)?.div(&count(len, nums[0].mode(), char_idx)?, char_idx);
+}
+
+// `percentile(v, 99)` interpolates linearly between the two closest items
+pub fn percentile(val: &Value, p: &CalcNumber, char_idx: usize) -> Result<Value, CalcError> {
+    let (nums, mode) = sorted(val, char_idx)?;
+    let p = p.convert(nums[0].mode());
+    let hundred = count(100, p.mode(), char_idx)?;
+    let zero = count(0, p.mode(), char_idx)?;
+    if p.compare(&zero).is_none_or(|ord| ord == Ordering::Less) || p.compare(&hundred) == Some(Ordering::Greater) {
+        return Err(CalcError::Domain(Span::at(char_idx)));
+    }
+    let rank = p.mul(&count(nums.len() - 1, p.mode(), char_idx)?, char_idx)?.div(&hundred, char_idx)?;
+    let lower = rank.floor();
+    let k = lower.to_big_integer(char_idx)?.try_into().unwrap_or(nums.len() - 1);
+    if k + 1 >= nums.len() { return narrow(nums[k].clone(), mode, char_idx); }
+    let fraction = rank.sub(&lower, char_idx)?;
+    let res = nums[k].add(&nums[k+1].sub(&nums[k], char_idx)?.mul(&fraction, char_idx)?, char_idx)?;
+    return narrow(res, mode, char_idx);
+}
+
+// Counts of the items in `bins` equal wide bins from the smallest to the largest item
+pub fn histogram(val: &Value, bins: &CalcNumber, mode: NumberMode, char_idx: usize) -> Result<Value, CalcError> {
+    let (nums, _) = sorted(val, char_idx)?;
+    let bins = match bins.to_big_integer(char_idx)? {
+        bins if bins > BigInt::zero() && bins <= BigInt::from(MAX_BINS) => usize::try_from(bins).unwrap(),
+        _ => { return Err(CalcError::Domain(Span::at(char_idx))); }
+    };
+    let (low, high) = (&nums[0], &nums[nums.len()-1]);
+    let width = high.sub(low, char_idx)?.div(&count(bins, low.mode(), char_idx)?, char_idx)?;
+    let mut counts = vec![0; bins];
+    for num in &nums {
+        // Every item is in the first bin when they are the same
+        let bin = if width.is_zero() { 0 } else {
+            num.sub(low, char_idx)?.div(&width, char_idx)?.floor().to_big_integer(char_idx)?.try_into().unwrap_or(bins - 1)
+        };
+        counts[bin.min(bins - 1)] += 1;
+    }
+    let counts = counts.into_iter().map(|len| count(len, mode, char_idx).map(Value::Number)).collect::<Result<Vec<Value>, CalcError>>()?;
+    return Ok(Value::List(counts));
+}
+
+// Pearson correlation of two lists with the same length
+pub fn correlation(x: &Value, y: &Value, char_idx: usize) -> Result<Value, CalcError> {
+    let (xs, mode) = numbers(x, char_idx)?;
+    let (ys, _) = numbers(y, char_idx)?;
+    if xs.len() != ys.len() { return Err(CalcError::Length(Span::at(char_idx))); }
+    if xs.len() < 2 { return Err(CalcError::Domain(Span::at(char_idx))); }
+    let (dx, dy) = (deviations(&xs, char_idx)?, deviations(&ys, char_idx)?);
+    let dot = |a: &[CalcNumber], b: &[CalcNumber]| -> Result<CalcNumber, CalcError> {
+        total(&a.iter().zip(b).map(|(l, r)| l.mul(r, char_idx)).collect::<Result<Vec<CalcNumber>, CalcError>>()?, char_idx)
+    };
+    let spread = root(&dot(&dx, &dx)?.mul(&dot(&dy, &dy)?, char_idx)?, mode, char_idx)?;
+    let res = dot(&dx, &dy)?.convert(spread.mode()).div(&spread, char_idx)?;
+    return narrow(res, mode, char_idx);
+}
+
+// Number of the items equal to `item`
+pub fn occurrences(val: &Value, item: &CalcNumber, mode: NumberMode, char_idx: usize) -> Result<Value, CalcError> {
+    let items = val.list(char_idx)?;
+    let len = items.iter().filter(|it| matches!(it, Value::Number(num) if num.compare(item) == Some(Ordering::Equal))).count();
+    return Ok(Value::Number(count(len, mode, char_idx)?));
+}
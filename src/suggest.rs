@@ -0,0 +1,40 @@
+// Number of single character insertions, deletions and substitutions which turn `a` into `b`
+pub fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j+1];
+            row[j+1] = if ca == *cb { diagonal } else { 1 + diagonal.min(above).min(row[j]) };
+            diagonal = above;
+        }
+    }
+    return row[b.len()];
+}
+
+// The candidate closest to a misspelled name, a third of its characters may differ at most
+// so the short names like `y` get no suggestion, almost every other short name would be one
+// equally close candidates are taken in alphabetical order, so the suggestion does not depend on the order of a map
+pub fn closest(name: &str, candidates: &[String]) -> Option<String> {
+    let limit = name.chars().count() / 3;
+    return candidates.iter()
+        .filter(|candidate| candidate.as_str() != name)
+        .map(|candidate| (distance(name, candidate), candidate))
+        .filter(|(dist, _)| *dist <= limit)
+        .min()
+        .map(|(_, candidate)| candidate.clone());
+}
+
+// The operators are too short for the limit of `closest`, so the same characters in another order
+// like `<=` for `=<` are suggested first
+pub fn closest_operator(token: &str, operators: &[String]) -> Option<String> {
+    let sorted = |op: &str| {
+        let mut chars: Vec<char> = op.chars().collect();
+        chars.sort();
+        chars
+    };
+    let swapped = operators.iter().find(|op| op.as_str() != token && sorted(op) == sorted(token));
+    return swapped.cloned().or_else(|| closest(token, operators));
+}
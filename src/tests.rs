@@ -1,580 +1,2387 @@
 use super::calc;
+use super::error::{CalcError, Span};
+use super::suggest;
+use super::number::{AngleMode, IntType, NumberMode, OutputBase, Overflow};
+
+fn signed() -> calc::Settings {
+    int(64, true, Overflow::Wrap)
+}
+
+fn int(bits: u32, signed: bool, overflow: Overflow) -> calc::Settings {
+    calc::Settings { mode: NumberMode::Int(IntType::new(bits, signed, overflow)), ..Default::default() }
+}
+
+fn float() -> calc::Settings {
+    calc::Settings { mode: NumberMode::Float, ..Default::default() }
+}
+
+fn big() -> calc::Settings {
+    calc::Settings { mode: NumberMode::Big, ..Default::default() }
+}
+
+fn rational() -> calc::Settings {
+    calc::Settings { mode: NumberMode::Rational, ..Default::default() }
+}
+
+fn complex() -> calc::Settings {
+    calc::Settings { mode: NumberMode::Complex, ..Default::default() }
+}
+
+// The message of an error and the char index it points at
+fn located(err: CalcError) -> (String, usize) {
+    (err.to_string(), err.span().start)
+}
+
+fn decimal(res: calc::Value) -> String {
+    match res {
+        calc::Value::Number(v) => v.to_decimal_string(),
+        other => other.to_string(),
+    }
+}
 
 #[test]
 fn get_number_test_1(){
-    assert_eq!(1, calc::eval("1".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("1".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn get_number_test_2(){
-    assert_eq!(1, calc::eval("((1))".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("((1))".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn get_number_test_3(){
-    assert_eq!(110200, calc::eval("1   1  0 2 00".to_string()).unwrap().0);
+    assert_eq!("110200", calc::eval("1   1  0 2 00".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn add_test_1(){
-    assert_eq!(3, calc::eval("1+2".to_string()).unwrap().0);
+    assert_eq!("3", calc::eval("1+2".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn add_test_2(){
-    assert_eq!(3, calc::eval("(1+2)".to_string()).unwrap().0);
+    assert_eq!("3", calc::eval("(1+2)".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn add_test_3(){
-    assert_eq!(3, calc::eval("(1)+(2)".to_string()).unwrap().0);
+    assert_eq!("3", calc::eval("(1)+(2)".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn add_test_4(){
-    assert_eq!(17, calc::eval("5+1 0  +2".to_string()).unwrap().0);
+    assert_eq!("17", calc::eval("5+1 0  +2".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn sub_test_1(){
-    assert_eq!(1, calc::eval("2-1".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("2-1".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn sub_test_2(){
-    assert_eq!(10, calc::eval("(10)-0".to_string()).unwrap().0);
+    assert_eq!("10", calc::eval("(10)-0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn sub_test_3(){
-    assert_eq!(11, calc::eval("((2 0)  -( (9)))".to_string()).unwrap().0);
+    assert_eq!("11", calc::eval("((2 0)  -( (9)))".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn sub_test_4(){
-    assert_eq!(4, calc::eval("1 0- 4-2".to_string()).unwrap().0);
+    assert_eq!("4", calc::eval("1 0- 4-2".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn mul_test_1(){
-    assert_eq!(2, calc::eval("2*1".to_string()).unwrap().0);
+    assert_eq!("2", calc::eval("2*1".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn mul_test_2(){
-    assert_eq!(0, calc::eval("(10)*0".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("(10)*0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn mul_test_3(){
-    assert_eq!(180, calc::eval("((2 0)  *( (9)))".to_string()).unwrap().0);
+    assert_eq!("180", calc::eval("((2 0)  *( (9)))".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn mul_test_4(){
-    assert_eq!(60, calc::eval("2*1 0*3".to_string()).unwrap().0);
+    assert_eq!("60", calc::eval("2*1 0*3".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn div_test_1(){
-    assert_eq!(5, calc::eval("1 0/ 2".to_string()).unwrap().0);
+    assert_eq!("5", calc::eval("1 0/ 2".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn div_test_2(){
-    assert_eq!(15, calc::eval("(1 5)/ 1".to_string()).unwrap().0);
+    assert_eq!("15", calc::eval("(1 5)/ 1".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn div_test_3(){
-    assert_eq!(61839061, calc::eval("((123678122)/2)".to_string()).unwrap().0);
+    assert_eq!("61839061", calc::eval("((123678122)/2)".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn div_test_4(){
-    assert_eq!(2, calc::eval("1 6/ 2/   4".to_string()).unwrap().0);
+    assert_eq!("2", calc::eval("1 6/ 2/   4".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn pow_test_1(){
-    assert_eq!(8, calc::eval("2 **(3)".to_string()).unwrap().0);
+    assert_eq!("8", calc::eval("2 **(3)".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn pow_test_2(){
-    assert_eq!(1, calc::eval("(((19128736)) **(0))".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("(((19128736)) **(0))".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn pow_test_3(){
-    assert_eq!(268435456, calc::eval("16 **7".to_string()).unwrap().0);
+    assert_eq!("268435456", calc::eval("16 **7".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn pow_test_4(){
-    assert_eq!(1, calc::eval("1 **839274".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("1 **839274".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn fact_test_1(){
-    assert_eq!(6, calc::eval("3!".to_string()).unwrap().0);
+    assert_eq!("6", calc::eval("3!".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn fact_test_2(){
-    assert_eq!(1, calc::eval("0!".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("0!".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn fact_test_3(){
-    assert_eq!(3628800, calc::eval("10!".to_string()).unwrap().0);
+    assert_eq!("3628800", calc::eval("10!".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn fact_test_4(){
-    assert_eq!(720, calc::eval("3!!".to_string()).unwrap().0);
+    assert_eq!("720", calc::eval("3!!".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn sqrt_test_1(){
-    assert_eq!(3, calc::eval("@9".to_string()).unwrap().0);
+    assert_eq!("3", calc::eval("@9".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn sqrt_test_2(){
-    assert_eq!(0, calc::eval("@0".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("@0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn sqrt_test_3(){
-    assert_eq!(20, calc::eval("@400".to_string()).unwrap().0);
+    assert_eq!("20", calc::eval("@400".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn sqrt_test_4(){
-    assert_eq!(23, calc::eval("@@279841".to_string()).unwrap().0);
+    assert_eq!("23", calc::eval("@@279841".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn and_test_1(){
-    assert_eq!(0, calc::eval("124&&0".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("124&&0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn and_test_2(){
-    assert_eq!(0, calc::eval("0&&2313".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("0&&2313".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn and_test_3(){
-    assert_eq!(0, calc::eval("0&&0".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("0&&0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn and_test_4(){
-    assert_eq!(1, calc::eval("124&&929842".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("124&&929842".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn and_test_5(){
-    assert_eq!(0, calc::eval("124&&929842&&0".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("124&&929842&&0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn or_test_1(){
-    assert_eq!(1, calc::eval("124||0".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("124||0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn or_test_2(){
-    assert_eq!(1, calc::eval("0||2313".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("0||2313".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn or_test_3(){
-    assert_eq!(0, calc::eval("0||0".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("0||0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn or_test_4(){
-    assert_eq!(1, calc::eval("124||929842".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("124||929842".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn or_test_5(){
-    assert_eq!(1, calc::eval("124||929842||0".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("124||929842||0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn xor_test_1(){
-    assert_eq!(1, calc::eval("124^^0".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("124^^0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn xor_test_2(){
-    assert_eq!(1, calc::eval("0^^2313".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("0^^2313".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn xor_test_3(){
-    assert_eq!(0, calc::eval("0^^0".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("0^^0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn xor_test_4(){
-    assert_eq!(0, calc::eval("124^^929842".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("124^^929842".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn xor_test_5(){
-    assert_eq!(0, calc::eval("124^^929842^^0".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("124^^929842^^0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn band_test_1(){
-    assert_eq!(0, calc::eval("124&0".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("124&0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn band_test_2(){
-    assert_eq!(1, calc::eval("5&3".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("5&3".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn band_test_3(){
-    assert_eq!(0, calc::eval("0&0".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("0&0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn band_test_4(){
-    assert_eq!(4145, calc::eval("12345&54321".to_string()).unwrap().0);
+    assert_eq!("4145", calc::eval("12345&54321".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn band_test_5(){
-    assert_eq!(0, calc::eval("124&929842&0".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("124&929842&0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn bor_test_1(){
-    assert_eq!(124, calc::eval("124|0".to_string()).unwrap().0);
+    assert_eq!("124", calc::eval("124|0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn bor_test_2(){
-    assert_eq!(7, calc::eval("5|3".to_string()).unwrap().0);
+    assert_eq!("7", calc::eval("5|3".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn bor_test_3(){
-    assert_eq!(0, calc::eval("0|0".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("0|0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn bor_test_4(){
-    assert_eq!(62521, calc::eval("12345|54321".to_string()).unwrap().0);
+    assert_eq!("62521", calc::eval("12345|54321".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn bor_test_5(){
-    assert_eq!(13, calc::eval("1|8|4".to_string()).unwrap().0);
+    assert_eq!("13", calc::eval("1|8|4".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn bxor_test_1(){
-    assert_eq!(124, calc::eval("124^0".to_string()).unwrap().0);
+    assert_eq!("124", calc::eval("124^0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn bxor_test_2(){
-    assert_eq!(6, calc::eval("5^3".to_string()).unwrap().0);
+    assert_eq!("6", calc::eval("5^3".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn bxor_test_3(){
-    assert_eq!(0, calc::eval("0^0".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("0^0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn bxor_test_4(){
-    assert_eq!(58376, calc::eval("12345^54321".to_string()).unwrap().0);
+    assert_eq!("58376", calc::eval("12345^54321".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn bxor_test_5(){
-    assert_eq!(0, calc::eval("30^20^10".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("30^20^10".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn mod_test_1(){
-    assert_eq!(0, calc::eval("10%1".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("10%1".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn mod_test_2(){
-    assert_eq!(0, calc::eval("10%5".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("10%5".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn mod_test_3(){
-    assert_eq!(3, calc::eval("3%4".to_string()).unwrap().0);
+    assert_eq!("3", calc::eval("3%4".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn mod_test_4(){
-    assert_eq!(1, calc::eval("4%3%2".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("4%3%2".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn equals_test_1(){
-    assert_eq!(1, calc::eval("0==0".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("0==0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn equals_test_2(){
-    assert_eq!(0, calc::eval("1==0".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("1==0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn equals_test_3(){
-    assert_eq!(0, calc::eval("1000==29634".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("1000==29634".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn equals_test_4(){
-    assert_eq!(1, calc::eval("1000==29634==0".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("1000==29634==0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn not_test_1(){
-    assert_eq!(0, calc::eval("~3".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("~3".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn not_test_2(){
-    assert_eq!(1, calc::eval("~0".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("~0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn not_test_3(){
-    assert_eq!(0, calc::eval("~987343".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("~987343".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn not_test_4(){
-    assert_eq!(1, calc::eval("~~987343".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("~~987343".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn not_test_5(){
+    // A negative value is true
+    assert_eq!("1", calc::eval_with("-1 && 1".to_string(), &signed()).unwrap().to_string());
+    assert_eq!("0", calc::eval_with("~(-5)".to_string(), &signed()).unwrap().to_string());
+    assert_eq!("10", calc::eval_with("if(-1, 10, 20)".to_string(), &signed()).unwrap().to_string());
+    assert_eq!("10", calc::eval_with("-0.5 ? 10 : 20".to_string(), &float()).unwrap().to_string());
+    assert_eq!("1", calc::eval_with("0 || -2.5".to_string(), &float()).unwrap().to_string());
+    assert_eq!("1", calc::eval_with("-1/2 ^^ 0".to_string(), &rational()).unwrap().to_string());
+    assert_eq!("0", calc::eval_with("~(-3)".to_string(), &big()).unwrap().to_string());
+    assert_eq!("[-1, 2]", calc::eval_with("filter(x -> x, [-1, 0, 2])".to_string(), &signed()).unwrap().to_string());
 }
 
 #[test]
 fn gt_test_1(){
-    assert_eq!(1, calc::eval("3>1".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("3>1".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn gt_test_2(){
-    assert_eq!(0, calc::eval("4>5".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("4>5".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn gt_test_3(){
-    assert_eq!(0, calc::eval("4>4".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("4>4".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn gt_test_4(){
-    assert_eq!(1, calc::eval("4>3>0".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("4>3>0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn ge_test_1(){
-    assert_eq!(1, calc::eval("3>=1".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("3>=1".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn ge_test_2(){
-    assert_eq!(0, calc::eval("4>=5".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("4>=5".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn ge_test_3(){
-    assert_eq!(1, calc::eval("4>=4".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("4>=4".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn ge_test_4(){
-    assert_eq!(1, calc::eval("4>=4>=1>=1>=0".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("4>=4>=1>=1>=0".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn lt_test_1(){
-    assert_eq!(0, calc::eval("3<1".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("3<1".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn lt_test_2(){
-    assert_eq!(1, calc::eval("4<5".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("4<5".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn lt_test_3(){
-    assert_eq!(0, calc::eval("4<4".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("4<4".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn lt_test_4(){
-    assert_eq!(1, calc::eval("1<2<2<2<2".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("1<2<2<2<2".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn le_test_1(){
-    assert_eq!(0, calc::eval("3<=1".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("3<=1".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn le_test_2(){
-    assert_eq!(1, calc::eval("4<=5".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("4<=5".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn le_test_3(){
-    assert_eq!(1, calc::eval("4<=4".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("4<=4".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn le_test_4(){
-    assert_eq!(1, calc::eval("2<=2<=1<=1<=1".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("2<=2<=1<=1<=1".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn ne_test_1(){
-    assert_eq!(1, calc::eval("3~=1".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("3~=1".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn ne_test_2(){
-    assert_eq!(1, calc::eval("4~=5".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("4~=5".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn ne_test_3(){
-    assert_eq!(0, calc::eval("4~=4".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("4~=4".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn ne_test_4(){
-    assert_eq!(0, calc::eval("2~=2~=4~=1".to_string()).unwrap().0);
+    assert_eq!("0", calc::eval("2~=2~=4~=1".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn precedence_test(){
-    assert_eq!(16, calc::eval("5*3+10-18/2".to_string()).unwrap().0);
+    assert_eq!("16", calc::eval("5*3+10-18/2".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn parenthesis_test_1(){
-    assert_eq!(25, calc::eval("10*(2+8)/4".to_string()).unwrap().0);
+    assert_eq!("25", calc::eval("10*(2+8)/4".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn parenthesis_test_2(){
-    assert_eq!(25, calc::eval("10*(2+8)/4".to_string()).unwrap().0);
+    assert_eq!("25", calc::eval("10*(2+8)/4".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn parenthesis_test_3(){
-    assert_eq!(1, calc::eval("(((2))**3)/((4)+2*(200/(2**6+8*4)))".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("(((2))**3)/((4)+2*(200/(2**6+8*4)))".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn expr_test_1(){
-    assert_eq!(4, calc::eval("(((2))**3)/((4)+2*(200/(2**6+8*4)))+(((2))**3)/((4)+2*(200/(2**6+8*4)))+(((2))**3)/((4)+2*(200/(2**6+8*4)))+(((2))**3)/((4)+2*(200/(2**6+8*4)))".to_string()).unwrap().0);
+    assert_eq!("4", calc::eval("(((2))**3)/((4)+2*(200/(2**6+8*4)))+(((2))**3)/((4)+2*(200/(2**6+8*4)))+(((2))**3)/((4)+2*(200/(2**6+8*4)))+(((2))**3)/((4)+2*(200/(2**6+8*4)))".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn expr_test_2(){
-    assert_eq!(164531, calc::eval("(1+2+3+4+5)**4*13/4".to_string()).unwrap().0);
+    assert_eq!("164531", calc::eval("(1+2+3+4+5)**4*13/4".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn expr_test_3(){
-    assert_eq!(1, calc::eval("(@@160 000*3+4)/8**2!".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("(@@160 000*3+4)/8**2!".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn expr_test_4(){
-    assert_eq!(1, calc::eval("3*1-3+2 ~= @16/2!-(6&3) && @(2**3*50)>=19".to_string()).unwrap().0);
+    assert_eq!("1", calc::eval("3*1-3+2 ~= @16/2!-(6&3) && @(2**3*50)>=19".to_string()).unwrap().to_string());
 }
 
 #[test]
 fn divided_by_zero_test(){
-    assert_eq!("Divided by zero", calc::eval("1/0".to_string()).unwrap_err().0);
+    assert_eq!("Divided by zero", calc::eval("1/0".to_string()).unwrap_err().to_string());
 }
 
 #[test]
 fn wrong_paren_test_1(){
-    assert_eq!("Wrong parenthesis found", calc::eval("(".to_string()).unwrap_err().0);
+    assert_eq!(("(: Parenthesis is never closed".to_string(), 0), located(calc::eval("(".to_string()).unwrap_err()));
 }
 
 #[test]
 fn wrong_paren_test_2(){
-    assert_eq!("Wrong parenthesis found", calc::eval("())".to_string()).unwrap_err().0);
+    assert_eq!(("): Parenthesis has no opening pair".to_string(), 2), located(calc::eval("())".to_string()).unwrap_err()));
 }
 
 #[test]
 fn no_result_test_1(){
-    assert_eq!("No Result", calc::eval("()".to_string()).unwrap_err().0);
+    assert_eq!("No Result", calc::eval("()".to_string()).unwrap_err().to_string());
 }
 
 #[test]
 fn arg_miss_test_1(){
-    assert_eq!("Argumentum is missing", calc::eval("()+1".to_string()).unwrap_err().0);
+    assert_eq!("Argumentum is missing", calc::eval("()+1".to_string()).unwrap_err().to_string());
 }
 
 #[test]
 fn arg_miss_test_2(){
-    assert_eq!("Argumentum is missing", calc::eval("()+1+()".to_string()).unwrap_err().0);
+    assert_eq!("Argumentum is missing", calc::eval("()+1+()".to_string()).unwrap_err().to_string());
 }
 
 #[test]
 fn arg_miss_test_3(){
-    assert_eq!("Argumentum is missing", calc::eval("1^()".to_string()).unwrap_err().0);
+    assert_eq!("Argumentum is missing", calc::eval("1^()".to_string()).unwrap_err().to_string());
 }
 
 #[test]
 fn arg_miss_test_4(){
-    assert_eq!("Argumentum is missing", calc::eval("()/()".to_string()).unwrap_err().0);
+    assert_eq!("Argumentum is missing", calc::eval("()/()".to_string()).unwrap_err().to_string());
 }
 
 #[test]
 fn arg_miss_test_5(){
-    assert_eq!("Argumentum is missing", calc::eval("()*()".to_string()).unwrap_err().0);
+    assert_eq!("Argumentum is missing", calc::eval("()*()".to_string()).unwrap_err().to_string());
 }
 
 #[test]
 fn arg_miss_test_6(){
-    assert_eq!("Argumentum is missing", calc::eval("()-()".to_string()).unwrap_err().0);
+    assert_eq!("Argumentum is missing", calc::eval("()-()".to_string()).unwrap_err().to_string());
 }
 
 #[test]
 fn token_parse_err_test_6(){
-    assert_eq!("error: Token cannot be parsed", calc::eval("error".to_string()).unwrap_err().0);
+    assert_eq!("error: Variable is not defined", calc::eval("error".to_string()).unwrap_err().to_string());
 }
 
 #[test]
 fn token_parse_err_test_7(){
-    assert_eq!("]: Token cannot be parsed", calc::eval("]".to_string()).unwrap_err().0);
+    assert_eq!(("]: Parenthesis has no opening pair".to_string(), 0), located(calc::eval("]".to_string()).unwrap_err()));
 }
 
 #[test]
 fn token_parse_err_test_8(){
-    assert_eq!("': Token cannot be parsed", calc::eval("'".to_string()).unwrap_err().0);
+    assert_eq!("': Token cannot be parsed", calc::eval("'".to_string()).unwrap_err().to_string());
+}
+
+#[test]
+fn token_parse_err_test_9(){
+    assert_eq!(("é: Token cannot be parsed".to_string(), 0), located(calc::eval("é".to_string()).unwrap_err()));
+    assert_eq!(("é: Token cannot be parsed".to_string(), 4), located(calc::eval("1 + é".to_string()).unwrap_err()));
+    // The indices count characters, not bytes
+    assert_eq!(vec![
+        CalcError::UnknownToken("é".to_string(), Span::at(0)),
+        CalcError::UnknownToken("ü".to_string(), Span::at(4)),
+        CalcError::UnknownVariable("nochkd".to_string(), Span::new(8, 14), None),
+    ], calc::check("é + ü + nochkd"));
+    assert_eq!(("é: Token cannot be parsed".to_string(), 8), located(calc::eval("naskv = é".to_string()).unwrap_err()));
+}
+
+#[test]
+fn token_parse_err_test_10(){
+    // An operand without an operator before it is not dropped
+    assert_eq!(("Token cannot be parsed".to_string(), 1), located(calc::eval("1e".to_string()).unwrap_err()));
+    assert_eq!(("Token cannot be parsed".to_string(), 1), located(calc::eval("1e+".to_string()).unwrap_err()));
+    assert_eq!(("Token cannot be parsed".to_string(), 29), located(calc::eval("compose(x -> x*2, x -> x + 1)(3)".to_string()).unwrap_err()));
+    assert_eq!(vec![CalcError::UnexpectedToken(Span::at(6))], calc::check("2 + 3 pi"));
+}
+
+#[test]
+fn token_parse_err_test_11(){
+    // The operand after an unknown operator is not reported again
+    assert_eq!(vec![CalcError::UnknownToken("=".to_string(), Span::at(2))], calc::check("1 = 2"));
+    assert_eq!(vec![CalcError::UnknownToken("=<".to_string(), Span::new(2, 4))], calc::check("1 =< 2"));
+    assert_eq!(vec![CalcError::UnknownToken("!=".to_string(), Span::new(2, 4))], calc::check("1 != 2"));
+    assert_eq!(vec![CalcError::UnknownToken("$".to_string(), Span::at(2))], calc::check("1 $ 2"));
+}
+
+#[test]
+fn error_kind_test_1(){
+    assert_eq!(CalcError::DivideByZero(Span::new(1, 2)), calc::eval("1/0".to_string()).unwrap_err());
+}
+
+#[test]
+fn error_kind_test_2(){
+    assert_eq!(CalcError::UnknownToken("'".to_string(), Span::new(2, 3)), calc::eval("1 '".to_string()).unwrap_err());
+}
+
+#[test]
+fn error_kind_test_3(){
+    assert_eq!(CalcError::UnknownVariable("nodef".to_string(), Span::new(4, 9), None), calc::eval("1 + nodef".to_string()).unwrap_err());
+}
+
+#[test]
+fn error_kind_test_4(){
+    assert!(matches!(calc::eval("1 +".to_string()), Err(CalcError::MissingOperand(_))));
+    assert!(matches!(calc::eval("(1 + 2".to_string()), Err(CalcError::UnclosedParen('(', _))));
+}
+
+#[test]
+fn diagnostic_test_1(){
+    let input = "2 + 10/0";
+    let err = calc::eval(input.to_string()).unwrap_err();
+    assert_eq!("      ^", err.span().underline(input));
+    assert_eq!("division by zero", err.label());
+}
+
+#[test]
+fn diagnostic_test_2(){
+    assert_eq!("    ^^^^^", Span::new(4, 9).underline("1 + nodef"));
+    // A span past the end of the line marks the last character
+    assert_eq!("  ^", Span::new(3, 4).underline("1 +"));
+}
+
+#[test]
+fn diagnostic_test_3(){
+    let err = calc::eval("1 = 2".to_string()).unwrap_err();
+    assert_eq!(CalcError::UnknownToken("=".to_string(), Span::new(2, 3)), err);
+    assert!(err.help().unwrap().starts_with("did you mean `==`?"));
+    assert_eq!(None, calc::eval("1/0".to_string()).unwrap_err().help());
+}
+
+#[test]
+fn paren_error_test_1(){
+    let err = calc::eval("(1 + [2, 3)]".to_string()).unwrap_err();
+    assert_eq!(CalcError::MismatchedParen(')', Span::at(5), Span::at(10)), err);
+    assert_eq!(Some((Span::at(5), "opened here")), err.related());
+}
+
+#[test]
+fn paren_error_test_2(){
+    // The last parenthesis which is still open is reported
+    assert_eq!(CalcError::UnclosedParen('(', Span::at(3)), calc::eval("max(1, (2 + 3)".to_string()).unwrap_err());
+    assert_eq!(CalcError::UnclosedParen('(', Span::at(13)), calc::eval("pfn(x) = 1 + (x".to_string()).unwrap_err());
+}
+
+#[test]
+fn paren_error_test_3(){
+    assert_eq!(CalcError::UnopenedParen(')', Span::at(16)), calc::eval("map(x -> x, [1])) + 1".to_string()).unwrap_err());
+    assert_eq!(CalcError::UnclosedParen('(', Span::at(3)), calc::eval("sum(i, 1..3, (i)".to_string()).unwrap_err());
+    assert_eq!(CalcError::MismatchedParen(']', Span::at(3), Span::at(11)), calc::eval("sum(i, 1..3]".to_string()).unwrap_err());
+}
+
+#[test]
+fn check_test_1(){
+    assert_eq!(vec![
+        CalcError::UnknownVariable("nochka".to_string(), Span::new(4, 10), None),
+        CalcError::UnknownVariable("nochkb".to_string(), Span::new(17, 23), None),
+    ], calc::check("1 + nochka * 2 + nochkb"));
+}
+
+#[test]
+fn check_test_2(){
+    // The errors are in the order of the line, not in the order they are found
+    assert_eq!(vec![
+        CalcError::MissingOperand(Span::at(3)),
+        CalcError::UnknownToken("'".to_string(), Span::at(9)),
+        CalcError::UnopenedParen(']', Span::at(13)),
+    ], calc::check("(1 + ) * ' 3 ]"));
+}
+
+#[test]
+fn check_test_3(){
+    assert_eq!(vec![
+        CalcError::UnexpectedToken(Span::at(2)),
+        CalcError::Arity(Span::at(4)),
+        CalcError::UnclosedParen('(', Span::at(19)),
+    ], calc::check("1 : abs(1, 2) + max(3"));
+}
+
+#[test]
+fn check_test_4(){
+    assert!(calc::check("1 + 2 * (3 - 1)").is_empty());
+    // Only the parsing is checked, the evaluation errors are left to `eval`
+    assert!(calc::check("1/0").is_empty());
+    assert_eq!(vec![CalcError::UnknownVariable("nochkc".to_string(), Span::new(0, 6), None)], calc::check("nochkc"));
+}
+
+#[test]
+fn check_test_5(){
+    // `eval` stops at the first error of the line
+    assert_eq!(CalcError::UnknownToken("'".to_string(), Span::at(9)), calc::eval("(1 + ) * ' 3".to_string()).unwrap_err());
+}
+
+#[test]
+fn suggest_test_1(){
+    assert_eq!(1, suggest::distance("totl", "total"));
+    assert_eq!(2, suggest::distance("mdian", "median_"));
+    assert_eq!(3, suggest::distance("", "abc"));
+    assert_eq!(0, suggest::distance("abc", "abc"));
+}
+
+#[test]
+fn suggest_test_2(){
+    let names = vec!["totals".to_string(), "total".to_string(), "totient".to_string()];
+    assert_eq!(Some("total".to_string()), suggest::closest("totl", &names));
+    assert_eq!(None, suggest::closest("xyzw", &names));
+    // The short names get no suggestion
+    assert_eq!(None, suggest::closest("to", &names));
+}
+
+#[test]
+fn suggest_test_3(){
+    calc::eval("sgtotal = 3".to_string()).unwrap();
+    assert_eq!("sgtotl: Variable is not defined, did you mean `sgtotal`?", calc::eval("sgtotl + 1".to_string()).unwrap_err().to_string());
+    assert_eq!("sgtotl: Variable is not defined, did you mean `sgtotal`?", calc::eval("sgtotl".to_string()).unwrap_err().to_string());
+}
+
+#[test]
+fn suggest_test_4(){
+    assert_eq!(CalcError::UnknownFunction("sqrtt".to_string(), Span::new(0, 5), Some("sqrt".to_string())), calc::eval("sqrtt(4)".to_string()).unwrap_err());
+    calc::eval("sgdouble(x) = x*2".to_string()).unwrap();
+    assert_eq!("sgduoble: Function is not defined, did you mean `sgdouble`?", calc::eval("sgduoble(2)".to_string()).unwrap_err().to_string());
+}
+
+#[test]
+fn suggest_test_5(){
+    let err = calc::eval("1 != 2".to_string()).unwrap_err();
+    assert_eq!(CalcError::UnknownToken("!=".to_string(), Span::new(2, 4)), err);
+    assert_eq!(Some("did you mean `~=`?".to_string()), err.help());
+    let err = calc::eval("3 => 2".to_string()).unwrap_err();
+    assert_eq!(CalcError::UnknownToken("=>".to_string(), Span::new(2, 4)), err);
+    assert_eq!(Some("did you mean `>=`? A function value is written like `x -> x*2`".to_string()), err.help());
+}
+
+#[test]
+fn suggest_test_6(){
+    let err = calc::eval("1 =~ 2".to_string()).unwrap_err();
+    assert_eq!(CalcError::UnknownToken("=~".to_string(), Span::new(2, 4)), err);
+    assert_eq!(Some("did you mean `~=`?".to_string()), err.help());
+    let operators: Vec<String> = ["<=", "<<", "<<<", "~"].iter().map(|op| op.to_string()).collect();
+    assert_eq!(Some("<<<".to_string()), suggest::closest_operator("<<<<", &operators));
+    assert_eq!(None, suggest::closest_operator("$", &operators));
 }
 
 #[test]
 fn var_test_1(){
-    assert_eq!(2, calc::eval("a=(2+3)-3".to_string()).unwrap().0);
-    assert_eq!(5, calc::eval("1+a*2".to_string()).unwrap().0);
-    assert_eq!(18, calc::eval("asd09 = 3*(1+2+3)".to_string()).unwrap().0);
-    assert_eq!(22, calc::eval("a*(a+asd09)-asd09".to_string()).unwrap().0);
-    assert_eq!(3, calc::eval("a=3".to_string()).unwrap().0);
-}
\ No newline at end of file
+    assert_eq!("2", calc::eval("a=(2+3)-3".to_string()).unwrap().to_string());
+    assert_eq!("5", calc::eval("1+a*2".to_string()).unwrap().to_string());
+    assert_eq!("18", calc::eval("asd09 = 3*(1+2+3)".to_string()).unwrap().to_string());
+    assert_eq!("22", calc::eval("a*(a+asd09)-asd09".to_string()).unwrap().to_string());
+    assert_eq!("3", calc::eval("a=3".to_string()).unwrap().to_string());
+}
+#[test]
+fn neg_test_1(){
+    assert_eq!("-2", calc::eval_with("-3+1".to_string(), &signed()).unwrap().to_string());
+}
+
+#[test]
+fn neg_test_2(){
+    assert_eq!("-3", calc::eval_with("2-5".to_string(), &signed()).unwrap().to_string());
+}
+
+#[test]
+fn neg_test_3(){
+    assert_eq!("-4", calc::eval_with("-2**2".to_string(), &signed()).unwrap().to_string());
+}
+
+#[test]
+fn neg_test_4(){
+    assert_eq!("6", calc::eval_with("-2*-(1+2)".to_string(), &signed()).unwrap().to_string());
+}
+
+#[test]
+fn neg_test_5(){
+    assert_eq!("4294967293", calc::eval("-3".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn neg_test_6(){
+    assert_eq!("1", calc::eval_with("3--2 == 5".to_string(), &signed()).unwrap().to_string());
+}
+
+#[test]
+fn negative_arg_test_1(){
+    assert_eq!("Argumentum cannot be negative", calc::eval_with("@-4".to_string(), &signed()).unwrap_err().to_string());
+}
+
+#[test]
+fn float_test_1(){
+    assert_eq!("3.64", calc::eval_with("3.14+.5".to_string(), &float()).unwrap().to_string());
+}
+
+#[test]
+fn float_test_2(){
+    assert_eq!("2.5", calc::eval_with("5/2".to_string(), &float()).unwrap().to_string());
+}
+
+#[test]
+fn float_test_3(){
+    assert_eq!("1e-9", calc::eval_with("1e-9".to_string(), &float()).unwrap().to_string());
+}
+
+#[test]
+fn float_test_4(){
+    assert_eq!("6.02e23", calc::eval_with("6.02E23".to_string(), &float()).unwrap().to_string());
+}
+
+#[test]
+fn float_test_5(){
+    assert_eq!("1.5", calc::eval_with("@2.25".to_string(), &float()).unwrap().to_string());
+}
+
+#[test]
+fn float_test_6(){
+    assert_eq!("-0.25", calc::eval_with("-2**-2".to_string(), &float()).unwrap().to_string());
+}
+
+#[test]
+fn float_test_7(){
+    assert_eq!("1", calc::eval_with("0.1+0.2 > 0.3 && 2.5e1 == 25".to_string(), &float()).unwrap().to_string());
+}
+
+#[test]
+fn float_test_8(){
+    assert_eq!("6", calc::eval_with("3!".to_string(), &float()).unwrap().to_string());
+}
+
+#[test]
+fn float_test_9(){
+    assert_eq!("inf", calc::eval_with("171!".to_string(), &float()).unwrap().to_string());
+    assert_eq!("inf", calc::eval_with("1e10!".to_string(), &float()).unwrap().to_string());
+    assert_eq!("1", calc::eval_with("170! < inf".to_string(), &float()).unwrap().to_string());
+}
+
+#[test]
+fn not_integer_test_1(){
+    assert_eq!("Argumentum must be an integer", calc::eval_with("1.5&1".to_string(), &float()).unwrap_err().to_string());
+}
+
+#[test]
+fn float_literal_in_integer_mode_test(){
+    assert_eq!("3.14: Token cannot be parsed", calc::eval("3.14".to_string()).unwrap_err().to_string());
+}
+
+#[test]
+fn big_test_1(){
+    assert_eq!("93326215443944152681699238856266700490715968264381621468592963895217599993229915608941463976156518286253697920827223758251185210916864000000000000000000000000", calc::eval_with("100!".to_string(), &big()).unwrap().to_string());
+}
+
+#[test]
+fn big_test_2(){
+    assert_eq!("1099511627776", calc::eval_with("2**40".to_string(), &big()).unwrap().to_string());
+}
+
+#[test]
+fn big_test_3(){
+    assert_eq!(302, calc::eval_with("2**1000".to_string(), &big()).unwrap().to_string().len());
+}
+
+#[test]
+fn big_test_4(){
+    assert_eq!("6227020800", calc::eval_with("13!".to_string(), &big()).unwrap().to_string());
+}
+
+#[test]
+fn big_test_5(){
+    assert_eq!("-1000000000000", calc::eval_with("1000000-1000000**2-1000000".to_string(), &big()).unwrap().to_string());
+}
+
+#[test]
+fn big_test_6(){
+    assert_eq!("1", calc::eval_with("@(10**40) == 10**20".to_string(), &big()).unwrap().to_string());
+}
+
+#[test]
+fn u32_wrapping_test(){
+    assert_eq!("1932053504", calc::eval("13!".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn width_test_1(){
+    assert_eq!("44", calc::eval_with("200+100".to_string(), &int(8, false, Overflow::Wrap)).unwrap().to_string());
+}
+
+#[test]
+fn width_test_2(){
+    assert_eq!("-32768", calc::eval_with("32767+1".to_string(), &int(16, true, Overflow::Wrap)).unwrap().to_string());
+}
+
+#[test]
+fn width_test_3(){
+    assert_eq!("340282366920938463463374607431768211455", calc::eval_with("0-1".to_string(), &int(128, false, Overflow::Wrap)).unwrap().to_string());
+}
+
+#[test]
+fn width_test_4(){
+    assert_eq!("0", calc::eval_with("2**64".to_string(), &int(64, false, Overflow::Wrap)).unwrap().to_string());
+}
+
+#[test]
+fn width_test_5(){
+    assert_eq!("-1", calc::eval_with("255&-1".to_string(), &int(8, true, Overflow::Wrap)).unwrap().to_string());
+}
+
+#[test]
+fn saturate_test_1(){
+    assert_eq!("255", calc::eval_with("200+100".to_string(), &int(8, false, Overflow::Saturate)).unwrap().to_string());
+}
+
+#[test]
+fn saturate_test_2(){
+    assert_eq!("-128", calc::eval_with("-100-100".to_string(), &int(8, true, Overflow::Saturate)).unwrap().to_string());
+}
+
+#[test]
+fn saturate_test_3(){
+    assert_eq!("-9223372036854775808", calc::eval_with("(-3)**41".to_string(), &int(64, true, Overflow::Saturate)).unwrap().to_string());
+}
+
+#[test]
+fn checked_test_1(){
+    assert_eq!(("Integer overflow".to_string(), 5), located(calc::eval_with("65535+1".to_string(), &int(16, false, Overflow::Checked)).unwrap_err()));
+}
+
+#[test]
+fn checked_test_2(){
+    assert_eq!(("Integer overflow".to_string(), 2), located(calc::eval_with("35!".to_string(), &int(128, false, Overflow::Checked)).unwrap_err()));
+}
+
+#[test]
+fn checked_test_3(){
+    assert_eq!(("Integer overflow".to_string(), 0), located(calc::eval_with("-1".to_string(), &int(32, false, Overflow::Checked)).unwrap_err()));
+}
+
+#[test]
+fn checked_test_4(){
+    assert_eq!("1307674368000", calc::eval_with("15!".to_string(), &int(64, true, Overflow::Checked)).unwrap().to_string());
+}
+
+#[test]
+fn checked_test_5(){
+    // A literal out of the range is reported at the literal
+    assert_eq!(("Integer overflow".to_string(), 4), located(calc::eval_with("1 + 300".to_string(), &int(8, false, Overflow::Checked)).unwrap_err()));
+    assert_eq!(vec![CalcError::Overflow(Span::new(4, 7))], calc::check_with("1 + 300", &int(8, false, Overflow::Checked)));
+    assert_eq!("44", calc::eval_with("300".to_string(), &int(8, false, Overflow::Wrap)).unwrap().to_string());
+}
+
+#[test]
+fn checked_test_6(){
+    // The smallest signed integers are literals of their type
+    assert_eq!("-128", calc::eval_with("-128".to_string(), &int(8, true, Overflow::Checked)).unwrap().to_string());
+    assert_eq!("-32768", calc::eval_with("-32768".to_string(), &int(16, true, Overflow::Checked)).unwrap().to_string());
+    assert_eq!("-2147483648", calc::eval_with("-2147483648".to_string(), &int(32, true, Overflow::Checked)).unwrap().to_string());
+    assert_eq!("-9223372036854775808", calc::eval_with("-9223372036854775808".to_string(), &int(64, true, Overflow::Checked)).unwrap().to_string());
+    assert_eq!("-170141183460469231731687303715884105728", calc::eval_with("-170141183460469231731687303715884105728".to_string(), &int(128, true, Overflow::Checked)).unwrap().to_string());
+    assert_eq!("-128", calc::eval_with("-128".to_string(), &int(8, true, Overflow::Saturate)).unwrap().to_string());
+    assert_eq!("-127", calc::eval_with("1 + -128".to_string(), &int(8, true, Overflow::Checked)).unwrap().to_string());
+}
+
+#[test]
+fn checked_test_7(){
+    assert_eq!(("Integer overflow".to_string(), 1), located(calc::eval_with("-129".to_string(), &int(8, true, Overflow::Checked)).unwrap_err()));
+    assert_eq!(("Integer overflow".to_string(), 0), located(calc::eval_with("128".to_string(), &int(8, true, Overflow::Checked)).unwrap_err()));
+    assert_eq!(("Integer overflow".to_string(), 0), located(calc::eval_with("--128".to_string(), &int(8, true, Overflow::Checked)).unwrap_err()));
+    // The power binds stronger than the minus
+    assert_eq!(("Integer overflow".to_string(), 1), located(calc::eval_with("-128**1".to_string(), &int(8, true, Overflow::Checked)).unwrap_err()));
+}
+
+#[test]
+fn rational_test_1(){
+    assert_eq!("1", calc::eval_with("1/3*3".to_string(), &rational()).unwrap().to_string());
+}
+
+#[test]
+fn rational_test_2(){
+    assert_eq!("1/2", calc::eval_with("1/3 + 1/6".to_string(), &rational()).unwrap().to_string());
+}
+
+#[test]
+fn rational_test_3(){
+    assert_eq!("-7/4", calc::eval_with("0.25-2".to_string(), &rational()).unwrap().to_string());
+}
+
+#[test]
+fn rational_test_4(){
+    assert_eq!("8/27", calc::eval_with("(2/3)**3".to_string(), &rational()).unwrap().to_string());
+}
+
+#[test]
+fn rational_test_5(){
+    assert_eq!("9/4", calc::eval_with("(2/3)**-2".to_string(), &rational()).unwrap().to_string());
+}
+
+#[test]
+fn rational_test_6(){
+    assert_eq!("3/5", calc::eval_with("@(9/25)".to_string(), &rational()).unwrap().to_string());
+}
+
+#[test]
+fn rational_test_7(){
+    assert_eq!("1", calc::eval_with("0.1+0.2 == 0.3".to_string(), &rational()).unwrap().to_string());
+}
+
+#[test]
+fn rational_decimal_test_1(){
+    assert_eq!("0.33333333333333333333", decimal(calc::eval_with("1/3".to_string(), &rational()).unwrap()));
+}
+
+#[test]
+fn rational_decimal_test_2(){
+    assert_eq!("-2.125", decimal(calc::eval_with("-17/8".to_string(), &rational()).unwrap()));
+}
+
+#[test]
+fn rational_decimal_test_3(){
+    assert_eq!("1.4142135623730950488", decimal(calc::eval_with("@2".to_string(), &rational()).unwrap()));
+}
+
+#[test]
+fn complex_test_1(){
+    assert_eq!("2+3i", calc::eval_with("2+3i".to_string(), &complex()).unwrap().to_string());
+}
+
+#[test]
+fn complex_test_2(){
+    assert_eq!("-1", calc::eval_with("i**2".to_string(), &complex()).unwrap().to_string());
+}
+
+#[test]
+fn complex_test_3(){
+    assert_eq!("2i", calc::eval_with("@-4".to_string(), &complex()).unwrap().to_string());
+}
+
+#[test]
+fn complex_test_4(){
+    assert_eq!("5-i", calc::eval_with("(1+i)*(2-3i)".to_string(), &complex()).unwrap().to_string());
+}
+
+#[test]
+fn complex_test_5(){
+    assert_eq!("0.5-0.5i", calc::eval_with("1/(1+i)".to_string(), &complex()).unwrap().to_string());
+}
+
+#[test]
+fn complex_test_6(){
+    assert_eq!("1", calc::eval_with("(2+i) == 2+1i && ~(i > 0)".to_string(), &complex()).unwrap().to_string());
+}
+
+#[test]
+fn complex_test_7(){
+    assert_eq!("Operation is not supported for this number", calc::eval_with("i%2".to_string(), &complex()).unwrap_err().to_string());
+}
+
+#[test]
+fn complex_literal_in_integer_mode_test(){
+    assert_eq!("3i: Token cannot be parsed", calc::eval("3i".to_string()).unwrap_err().to_string());
+}
+
+#[test]
+fn radix_literal_test_1(){
+    assert_eq!("31", calc::eval("0x1F".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn radix_literal_test_2(){
+    assert_eq!("11", calc::eval("0b1011".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn radix_literal_test_3(){
+    assert_eq!("493", calc::eval("0o755".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn radix_literal_test_4(){
+    assert_eq!("1000001", calc::eval("1_000_000 + 0b1".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn radix_literal_test_5(){
+    assert_eq!("255", calc::eval("0xFF & 0b1111_0000 | 0XF0 ^ 0O377 & 0xf".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn radix_literal_test_6(){
+    assert_eq!("-1", calc::eval_with("0xFFFF_FFFF".to_string(), &int(32, true, Overflow::Checked)).unwrap().to_string());
+}
+
+#[test]
+fn radix_literal_test_7(){
+    assert_eq!("255", calc::eval_with("0xff".to_string(), &float()).unwrap().to_string());
+}
+
+#[test]
+fn radix_literal_test_8(){
+    // A literal with the bits of the type is its bit pattern
+    assert_eq!("-1", calc::eval_with("0xFF".to_string(), &int(8, true, Overflow::Checked)).unwrap().to_string());
+    assert_eq!("-128", calc::eval_with("0b1000_0000".to_string(), &int(8, true, Overflow::Checked)).unwrap().to_string());
+    // A wider one follows the overflow policy
+    assert_eq!(("Integer overflow".to_string(), 4), located(calc::eval_with("1 + 0x1FF".to_string(), &int(8, true, Overflow::Checked)).unwrap_err()));
+    assert_eq!(vec![CalcError::Overflow(Span::new(0, 5))], calc::check_with("0x1FF", &int(8, false, Overflow::Checked)));
+    assert_eq!("127", calc::eval_with("0x1FF".to_string(), &int(8, true, Overflow::Saturate)).unwrap().to_string());
+    assert_eq!("255", calc::eval_with("0o777".to_string(), &int(8, false, Overflow::Saturate)).unwrap().to_string());
+    assert_eq!("-1", calc::eval_with("0x1FF".to_string(), &int(8, true, Overflow::Wrap)).unwrap().to_string());
+}
+
+#[test]
+fn char_idx_test_1(){
+    assert_eq!(("Divided by zero".to_string(), 9), located(calc::eval("0x10 +  1/0".to_string()).unwrap_err()));
+}
+
+#[test]
+fn char_idx_test_2(){
+    assert_eq!(("Integer overflow".to_string(), 12), located(calc::eval_with("0b1111_1111 + 1".to_string(), &int(8, false, Overflow::Checked)).unwrap_err()));
+}
+
+#[test]
+fn char_idx_test_3(){
+    assert_eq!(("Divided by zero".to_string(), 10), located(calc::eval("idxvar = 1/0".to_string()).unwrap_err()));
+}
+
+#[test]
+fn output_base_test_1(){
+    assert_eq!("0xFF", calc::format_result(&calc::eval("255".to_string()).unwrap(), OutputBase::from_name("hex")));
+}
+
+#[test]
+fn output_base_test_2(){
+    assert_eq!("0b101", calc::format_result(&calc::eval("5".to_string()).unwrap(), OutputBase::from_name("bin")));
+}
+
+#[test]
+fn output_base_test_3(){
+    assert_eq!("0o755", calc::format_result(&calc::eval("493".to_string()).unwrap(), OutputBase::from_name("oct")));
+}
+
+#[test]
+fn output_base_test_4(){
+    assert_eq!("ZZ", calc::format_result(&calc::eval("1295".to_string()).unwrap(), OutputBase::from_name("36")));
+}
+
+#[test]
+fn output_base_test_5(){
+    assert_eq!("0xFF", calc::format_result(&calc::eval_with("-1".to_string(), &int(8, true, Overflow::Wrap)).unwrap(), OutputBase::from_name("hex")));
+}
+
+#[test]
+fn output_base_test_6(){
+    assert_eq!("-0xFF", calc::format_result(&calc::eval_with("-255".to_string(), &big()).unwrap(), OutputBase::from_name("hex")));
+}
+
+#[test]
+fn output_base_test_7(){
+    assert_eq!("2.5", calc::format_result(&calc::eval_with("5/2".to_string(), &float()).unwrap(), OutputBase::from_name("hex")));
+}
+
+#[test]
+fn output_base_test_8(){
+    assert_eq!("dec -2\nhex 0xFE\noct 0o376\nbin 0b1111_1110",
+        calc::format_result(&calc::eval_with("-2".to_string(), &int(8, true, Overflow::Wrap)).unwrap(), OutputBase::from_name("all")));
+}
+
+#[test]
+fn output_base_test_9(){
+    assert_eq!("dec 74565\nhex 0x1_2345\noct 0o221505\nbin 0b0001_0010_0011_0100_0101",
+        calc::format_result(&calc::eval_with("0x12345".to_string(), &big()).unwrap(), OutputBase::from_name("all")));
+}
+
+#[test]
+fn output_base_test_10(){
+    assert_eq!(("1 + 2".to_string(), OutputBase::from_name("hex")), calc::split_base_suffix("1 + 2 as hex"));
+}
+
+#[test]
+fn output_base_test_11(){
+    assert_eq!(("1 + 2 as foo".to_string(), None), calc::split_base_suffix("1 + 2 as foo"));
+}
+
+#[test]
+fn shift_test_1(){
+    assert_eq!("40", calc::eval("5 << 3".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn shift_test_2(){
+    assert_eq!("4", calc::eval("0x80 >> 5".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn shift_test_3(){
+    assert_eq!("32", calc::eval("1 << 2 + 3".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn shift_test_4(){
+    assert_eq!("1", calc::eval("1 << 4 > 15".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn shift_test_5(){
+    assert_eq!("0", calc::eval_with("1 << 8".to_string(), &int(8, false, Overflow::Checked)).unwrap().to_string());
+}
+
+#[test]
+fn shift_test_6(){
+    assert_eq!("-4", calc::eval_with("-16 >> 2".to_string(), &signed()).unwrap().to_string());
+}
+
+#[test]
+fn shift_test_7(){
+    assert_eq!("1267650600228229401496703205376", calc::eval_with("1 << 100".to_string(), &big()).unwrap().to_string());
+}
+
+#[test]
+fn shift_test_8(){
+    assert_eq!(("Argumentum cannot be negative".to_string(), 2), located(calc::eval_with("1 << -1".to_string(), &signed()).unwrap_err()));
+}
+
+#[test]
+fn shift_test_9(){
+    assert_eq!(("Argumentum must be an integer".to_string(), 4), located(calc::eval_with("1.5 << 1".to_string(), &float()).unwrap_err()));
+}
+
+#[test]
+fn rotate_test_1(){
+    assert_eq!("3", calc::eval_with("0x81 <<< 1".to_string(), &int(8, false, Overflow::Wrap)).unwrap().to_string());
+}
+
+#[test]
+fn rotate_test_2(){
+    assert_eq!("192", calc::eval_with("0x81 >>> 1".to_string(), &int(8, false, Overflow::Wrap)).unwrap().to_string());
+}
+
+#[test]
+fn rotate_test_3(){
+    assert_eq!("2147483648", calc::eval("1 >>> 33".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn rotate_test_4(){
+    assert_eq!("1", calc::eval_with("1 <<< 128".to_string(), &int(128, false, Overflow::Wrap)).unwrap().to_string());
+}
+
+#[test]
+fn rotate_test_5(){
+    assert_eq!(("Operation is not supported for this number".to_string(), 2), located(calc::eval_with("1 <<< 1".to_string(), &big()).unwrap_err()));
+}
+
+#[test]
+fn bit_not_test_1(){
+    assert_eq!("4294967295", calc::eval("!0".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn bit_not_test_2(){
+    assert_eq!("240", calc::eval_with("!0x0F".to_string(), &int(8, false, Overflow::Wrap)).unwrap().to_string());
+}
+
+#[test]
+fn bit_not_test_3(){
+    assert_eq!("-6", calc::eval_with("!5".to_string(), &big()).unwrap().to_string());
+}
+
+#[test]
+fn bit_not_test_4(){
+    assert_eq!("121", calc::eval("!!5! + 1".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn function_test_1(){
+    assert_eq!("9", calc::eval("max(3, 9, 4)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn function_test_2(){
+    assert_eq!("-1", calc::eval_with("min(3, -1, 4 - 2)".to_string(), &signed()).unwrap().to_string());
+}
+
+#[test]
+fn function_test_3(){
+    assert_eq!("9", calc::eval_with("2*max(1, 2) + abs(-5)".to_string(), &signed()).unwrap().to_string());
+}
+
+#[test]
+fn function_test_4(){
+    assert_eq!("6", calc::eval("gcd(12, 18)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn function_test_5(){
+    assert_eq!("60", calc::eval("lcm(4, 6, 10)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn function_test_6(){
+    assert_eq!("1024", calc::eval("pow(2, 10)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn function_test_7(){
+    assert_eq!("3", calc::eval("log(1000)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn function_test_8(){
+    assert_eq!("4", calc::eval("log(81, 3) + log2(1)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn function_test_9(){
+    assert_eq!("3", calc::eval_with("log(1000)".to_string(), &float()).unwrap().to_string());
+}
+
+#[test]
+fn function_test_10(){
+    assert_eq!("-3", calc::eval_with("cbrt(-27)".to_string(), &signed()).unwrap().to_string());
+}
+
+#[test]
+fn function_test_11(){
+    assert_eq!("2/3", calc::eval_with("cbrt(8/27)".to_string(), &rational()).unwrap().to_string());
+}
+
+#[test]
+fn function_test_12(){
+    assert_eq!("1", calc::eval_with("exp(ln(1))".to_string(), &float()).unwrap().to_string());
+}
+
+#[test]
+fn function_test_13(){
+    assert_eq!("7", calc::eval("exp(2)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn function_test_14(){
+    assert_eq!("3 -3 4 -3 3 -4", calc::eval_with("floor(3.7)".to_string(), &float()).unwrap().to_string() + " " +
+        &calc::eval_with("ceil(-3.7)".to_string(), &float()).unwrap().to_string() + " " +
+        &calc::eval_with("round(3.5)".to_string(), &float()).unwrap().to_string() + " " +
+        &calc::eval_with("ceil(-7/2)".to_string(), &rational()).unwrap().to_string() + " " +
+        &calc::eval_with("floor(7/2)".to_string(), &rational()).unwrap().to_string() + " " +
+        &calc::eval_with("round(-7/2)".to_string(), &rational()).unwrap().to_string());
+}
+
+#[test]
+fn function_test_15(){
+    assert_eq!("-1", calc::eval_with("sign(-7) + sign(0)".to_string(), &signed()).unwrap().to_string());
+}
+
+#[test]
+fn function_test_16(){
+    assert_eq!("10", calc::eval("clamp(15, 0, 10)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn function_test_17(){
+    assert_eq!("5", calc::eval_with("abs(3 + 4i)".to_string(), &complex()).unwrap().to_string());
+}
+
+#[test]
+fn function_test_18(){
+    assert_eq!("4", calc::eval("sqrt (16)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn function_test_19(){
+    assert_eq!("7", calc::eval("max(min(7, 9), max(1, 2, 3))".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn function_arity_test_1(){
+    assert_eq!(("Wrong number of arguments".to_string(), 0), located(calc::eval("abs(1, 2)".to_string()).unwrap_err()));
+}
+
+#[test]
+fn function_arity_test_2(){
+    assert_eq!(("Wrong number of arguments".to_string(), 4), located(calc::eval("1 + pow(2)".to_string()).unwrap_err()));
+}
+
+#[test]
+fn function_arity_test_3(){
+    assert_eq!(("Wrong number of arguments".to_string(), 0), located(calc::eval("max()".to_string()).unwrap_err()));
+}
+
+#[test]
+fn function_arity_test_4(){
+    assert_eq!(("Argumentum is missing".to_string(), 0), located(calc::eval("max(1, )".to_string()).unwrap_err()));
+}
+
+#[test]
+fn function_error_test_1(){
+    assert_eq!(("foo: Function is not defined".to_string(), 0), located(calc::eval("foo(1)".to_string()).unwrap_err()));
+}
+
+#[test]
+fn function_error_test_2(){
+    assert_eq!(("Token cannot be parsed".to_string(), 2), located(calc::eval("(1, 2)".to_string()).unwrap_err()));
+}
+
+#[test]
+fn function_error_test_3(){
+    assert_eq!(("Argumentum cannot be negative".to_string(), 0), located(calc::eval_with("log(-8)".to_string(), &signed()).unwrap_err()));
+}
+
+#[test]
+fn function_error_test_4(){
+    assert_eq!(("Argumentum is out of the domain".to_string(), 0), located(calc::eval_with("ln(0)".to_string(), &float()).unwrap_err()));
+}
+
+#[test]
+fn function_error_test_5(){
+    assert_eq!(("Argumentum is out of the domain".to_string(), 0), located(calc::eval("clamp(1, 5, 2)".to_string()).unwrap_err()));
+}
+
+#[test]
+fn user_function_test_1(){
+    assert_eq!("ufa(x, y)", calc::eval("ufa(x, y) = x**2 + y".to_string()).unwrap().to_string());
+    assert_eq!("13", calc::eval("ufa(3, 4)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn user_function_test_2(){
+    calc::eval("ufb(x) = 2*x - 1".to_string()).unwrap();
+    assert_eq!("18", calc::eval("ufb(ufb(3)) + max(ufb(1), 1) * 9 - 0".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn user_function_test_3(){
+    calc::eval("ufc(x) = x / 2".to_string()).unwrap();
+    assert_eq!("2.5", calc::eval_with("ufc(5)".to_string(), &float()).unwrap().to_string());
+    assert_eq!("2", calc::eval("ufc(5)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn user_function_test_4(){
+    calc::eval("ufd(a, b) = a - b".to_string()).unwrap();
+    calc::eval("ufe(a) = ufd(a, 1) * ufd(10, a)".to_string()).unwrap();
+    assert_eq!("14", calc::eval("ufe(3)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn user_function_test_5(){
+    calc::eval("uff() = 42".to_string()).unwrap();
+    assert_eq!("43", calc::eval("uff() + 1".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn user_function_test_6(){
+    calc::eval("ufg(x) = x + 1".to_string()).unwrap();
+    assert_eq!("1", calc::eval("ufg(1) == 2".to_string()).unwrap().to_string());
+    assert_eq!("ufg(x)", calc::eval("ufg".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn user_function_test_7(){
+    calc::eval("ufh(n) = n".to_string()).unwrap();
+    calc::eval("ufh(x, y) = x * y".to_string()).unwrap();
+    assert_eq!("12", calc::eval("ufh(3, 4)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn user_function_test_8(){
+    calc::eval("ufi(x, y) = x - y".to_string()).unwrap();
+    assert!(calc::get_funcs().contains(&"ufi(x, y) = x - y".to_string()));
+    assert!(calc::get_vars().contains(&"ufi(x, y): x - y => Function".to_string()));
+}
+
+#[test]
+fn user_function_error_test_1(){
+    calc::eval("ufj(x, y) = x + y".to_string()).unwrap();
+    assert_eq!(("Wrong number of arguments".to_string(), 4), located(calc::eval("1 + ufj(1)".to_string()).unwrap_err()));
+}
+
+#[test]
+fn user_function_error_test_2(){
+    calc::eval("ufk(x) = x".to_string()).unwrap();
+    assert_eq!(("Value is not a number".to_string(), 4), located(calc::eval("ufk + 1".to_string()).unwrap_err()));
+}
+
+#[test]
+fn user_function_error_test_3(){
+    assert_eq!(("y: Variable is not defined".to_string(), 13), located(calc::eval("ufl(x) = x + y".to_string()).unwrap_err()));
+}
+
+#[test]
+fn user_function_error_test_4(){
+    // The calls the parser builds itself cannot be replaced
+    assert_eq!(("sum: Name is reserved for a built-in".to_string(), 0), located(calc::eval("sum(x) = x".to_string()).unwrap_err()));
+    assert_eq!(("range: Name is reserved for a built-in".to_string(), 0), located(calc::eval("range(a, b) = a".to_string()).unwrap_err()));
+}
+
+#[test]
+fn user_function_error_test_5(){
+    calc::eval("ufm(x) = 10 / x".to_string()).unwrap();
+    assert_eq!(("Divided by zero".to_string(), 4), located(calc::eval("1 + ufm(0)".to_string()).unwrap_err()));
+}
+
+#[test]
+fn user_function_error_test_6(){
+    assert_eq!(CalcError::DuplicateParam("x".to_string(), Span::at(9)), calc::eval("dupfnq(x,x) = 1".to_string()).unwrap_err());
+    assert_eq!(vec![CalcError::DuplicateParam("ab".to_string(), Span::new(15, 17))], calc::check("dupfnq(ab, y,  ab) = ab + y"));
+    assert_eq!(("dupfnq: Function is not defined".to_string(), 0), located(calc::eval("dupfnq(1, 1)".to_string()).unwrap_err()));
+    // The function values are checked too
+    assert_eq!(vec![CalcError::DuplicateParam("a".to_string(), Span::at(8))], calc::check("map((a, a) -> a, [1])"));
+    assert_eq!("a: Parameter is repeated", calc::eval("(a, a) -> a".to_string()).unwrap_err().to_string());
+}
+
+#[test]
+fn recursion_test_1(){
+    calc::eval("rfib(n) = n < 2 ? n : rfib(n-1) + rfib(n-2)".to_string()).unwrap();
+    assert_eq!("55", calc::eval("rfib(10)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn recursion_test_2(){
+    calc::eval("rcount(n) = if(n == 0, 0, 1 + rcount(n - 1))".to_string()).unwrap();
+    let settings = calc::Settings { max_depth: 10000, ..Default::default() };
+    assert_eq!("9999", calc::eval_with("rcount(9999)".to_string(), &settings).unwrap().to_string());
+}
+
+#[test]
+fn recursion_test_3(){
+    calc::eval("rloop(n) = rloop(n + 1)".to_string()).unwrap();
+    assert_eq!(("Maximum recursion depth exceeded".to_string(), 0), located(calc::eval("rloop(0)".to_string()).unwrap_err()));
+}
+
+#[test]
+fn recursion_test_4(){
+    calc::eval("rdepth(n) = n == 0 ? 0 : rdepth(n - 1)".to_string()).unwrap();
+    let settings = calc::Settings { max_depth: 10, ..Default::default() };
+    assert_eq!("0", calc::eval_with("rdepth(9)".to_string(), &settings).unwrap().to_string());
+    assert_eq!(("Maximum recursion depth exceeded".to_string(), 0), located(calc::eval_with("rdepth(10)".to_string(), &settings).unwrap_err()));
+}
+
+#[test]
+fn recursion_test_5(){
+    calc::eval("rfact(n) = n <= 1 ? 1 : n * rfact(n - 1)".to_string()).unwrap();
+    assert_eq!("2432902008176640000", calc::eval_with("rfact(20)".to_string(), &big()).unwrap().to_string());
+}
+
+#[test]
+fn recursion_test_6(){
+    calc::eval("rmap(n) = n == 0 ? 0 : 1 + map(x -> rmap(x), [n-1])[0]".to_string()).unwrap();
+    let settings = calc::Settings { max_depth: 10000, ..Default::default() };
+    assert_eq!("3000", calc::eval_with("rmap(3000)".to_string(), &settings).unwrap().to_string());
+    assert_eq!("Maximum recursion depth exceeded", calc::eval_with("rmap(9000)".to_string(), &settings).unwrap_err().to_string());
+}
+
+#[test]
+fn condition_test_1(){
+    assert_eq!("10", calc::eval("1 < 2 ? 10 : 20".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn condition_test_2(){
+    assert_eq!("23", calc::eval("3 + (0 ? 10 : 20)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn condition_test_3(){
+    assert_eq!("2", calc::eval("0 ? 1 : 1 ? 2 : 3".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn condition_test_4(){
+    assert_eq!("3", calc::eval("1 ? 0 ? 2 : 3 : 4".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn condition_test_5(){
+    assert_eq!("5", calc::eval("if(1, 5, 1/0)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn condition_test_6(){
+    assert_eq!("7", calc::eval("0 ? 1/0 : 7".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn condition_test_7(){
+    assert_eq!("11", calc::eval("max(1 ? 11 : 2, 3)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn condition_error_test_1(){
+    assert_eq!(("Condition is missing its `:` branch".to_string(), 2), located(calc::eval("1 ? 2".to_string()).unwrap_err()));
+}
+
+#[test]
+fn condition_error_test_2(){
+    assert_eq!(("Token cannot be parsed".to_string(), 2), located(calc::eval("1 : 2".to_string()).unwrap_err()));
+}
+
+#[test]
+fn condition_error_test_3(){
+    assert_eq!(("Wrong number of arguments".to_string(), 0), located(calc::eval("if(1, 2)".to_string()).unwrap_err()));
+}
+
+#[test]
+fn lambda_test_1(){
+    assert_eq!("x -> x*2", calc::eval("ldouble = x -> x*2".to_string()).unwrap().to_string());
+    assert_eq!("42", calc::eval("ldouble(21)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn lambda_test_2(){
+    assert_eq!("(a, b) -> a + b", calc::eval("(a, b) -> a + b".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn lambda_test_3(){
+    calc::eval("ltwice(f, x) = f(f(x))".to_string()).unwrap();
+    assert_eq!("18", calc::eval("ltwice(y -> y*3, 2)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn lambda_test_4(){
+    calc::eval("lscale(n) = map(x -> x*n, range(1, 3))".to_string()).unwrap();
+    assert_eq!("[10, 20, 30]", calc::eval("lscale(10)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn lambda_test_5(){
+    calc::eval("lsq(x) = x*x".to_string()).unwrap();
+    assert_eq!("[1, 4, 9]", calc::eval("map(lsq, range(1, 3))".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn lambda_test_6(){
+    assert_eq!("[1/2, 3/2]", calc::eval_with("map(x -> x / 2, list(1, 3))".to_string(), &rational()).unwrap().to_string());
+}
+
+#[test]
+fn map_test_1(){
+    assert_eq!("[1, 4, 9]", calc::eval("map(x -> x*x, list(1, 2, 3))".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn map_test_2(){
+    assert_eq!("[1, 2, 3]", calc::eval_with("map(abs, list(-1, 2, -3))".to_string(), &signed()).unwrap().to_string());
+}
+
+#[test]
+fn filter_test_1(){
+    assert_eq!("[2, 4, 6, 8, 10]", calc::eval("filter(x -> x % 2 == 0, range(1, 10))".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn fold_test_1(){
+    assert_eq!("5050", calc::eval("fold((acc, x) -> acc + x, 0, range(1, 100))".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn fold_test_2(){
+    assert_eq!("9", calc::eval("fold(max, 0, list(3, 9, 4))".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn sum_over_test_1(){
+    assert_eq!("385", calc::eval("sum_over(range(1, 10), x -> x**2)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn compose_test_1(){
+    calc::eval("linc = x -> x + 1".to_string()).unwrap();
+    calc::eval("lcomp = compose(x -> x*2, linc)".to_string()).unwrap();
+    assert_eq!("8", calc::eval("lcomp(3)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn range_test_1(){
+    assert_eq!("[5, 3, 1]", calc::eval_with("range(5, 1, -2)".to_string(), &signed()).unwrap().to_string());
+}
+
+#[test]
+fn range_test_2(){
+    assert_eq!("[253, 254, 255]", calc::eval_with("range(253, 255)".to_string(), &int(8, false, Overflow::Wrap)).unwrap().to_string());
+}
+
+#[test]
+fn lambda_error_test_1(){
+    assert_eq!(("Value is not a function".to_string(), 0), located(calc::eval("map(3, list(1))".to_string()).unwrap_err()));
+}
+
+#[test]
+fn lambda_error_test_2(){
+    assert_eq!(("Value is not a list".to_string(), 0), located(calc::eval("map(x -> x, 5)".to_string()).unwrap_err()));
+}
+
+#[test]
+fn lambda_error_test_3(){
+    assert_eq!(("Value is not a number".to_string(), 9), located(calc::eval("(x -> x) + 1".to_string()).unwrap_err()));
+}
+
+#[test]
+fn lambda_error_test_4(){
+    assert_eq!(("Wrong number of arguments".to_string(), 0), located(calc::eval("map((x, y) -> x, list(1))".to_string()).unwrap_err()));
+}
+
+#[test]
+fn lambda_test_7(){
+    calc::eval("lcurry = a -> b -> a - b".to_string()).unwrap();
+    calc::eval("lcurry10 = lcurry(10)".to_string()).unwrap();
+    assert_eq!("7", calc::eval("lcurry10(3)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn list_test_1(){
+    assert_eq!("[1, 2, 3]", calc::eval("[1, 2, 3]".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn list_test_2(){
+    assert_eq!("[]", calc::eval("[]".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn list_test_3(){
+    assert_eq!("[[1, 2], [3]]", calc::eval("[[1, 2], [1 + 2]]".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn list_test_4(){
+    assert_eq!("3", calc::eval("len([4, 5, 6])".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn list_test_5(){
+    assert_eq!("0", calc::eval_with("len([])".to_string(), &big()).unwrap().to_string());
+}
+
+#[test]
+fn index_test_1(){
+    calc::eval("lv = [10, 20, 30]".to_string()).unwrap();
+    assert_eq!("20", calc::eval("lv[1]".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn index_test_2(){
+    assert_eq!("30", calc::eval_with("[10, 20, 30][-1]".to_string(), &signed()).unwrap().to_string());
+}
+
+#[test]
+fn index_test_3(){
+    assert_eq!("-4", calc::eval_with("-[[1, 2], [3, 4]][1][1]".to_string(), &signed()).unwrap().to_string());
+}
+
+#[test]
+fn index_test_4(){
+    assert_eq!("41", calc::eval("1 + [10, 20][0] * 2**2".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn slice_test_1(){
+    assert_eq!("[2, 3]", calc::eval("[1, 2, 3, 4][1:3]".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn slice_test_2(){
+    assert_eq!("[1, 2]", calc::eval("[1, 2, 3, 4][:2]".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn slice_test_3(){
+    assert_eq!("[3, 4]", calc::eval_with("[1, 2, 3, 4][-2:]".to_string(), &signed()).unwrap().to_string());
+}
+
+#[test]
+fn slice_test_4(){
+    assert_eq!("[]", calc::eval("[1, 2, 3, 4][3:1]".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn slice_test_5(){
+    assert_eq!("2", calc::eval("[1, 2, 3][0 ? 0 : 1]".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn concat_test_1(){
+    assert_eq!("[1, 2, 3, 4]", calc::eval("[1, 2] ++ [3] ++ 4".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn concat_test_2(){
+    assert_eq!("[1, 2, 3]", calc::eval("concat([1], [2, 3])".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn broadcast_test_1(){
+    assert_eq!("[2, 4, 6]", calc::eval("[1, 2, 3] * 2".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn broadcast_test_2(){
+    assert_eq!("[11, 22]", calc::eval("[1, 2] + [10, 20]".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn broadcast_test_3(){
+    assert_eq!("[0, 1, 1]", calc::eval("2 <= [1, 2, 3]".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn broadcast_test_4(){
+    assert_eq!("[-1, [-2, -3]]", calc::eval_with("-[1, [2, 3]]".to_string(), &signed()).unwrap().to_string());
+}
+
+#[test]
+fn list_error_test_1(){
+    assert_eq!(("Lists have different lengths".to_string(), 7), located(calc::eval("[1, 2] + [1]".to_string()).unwrap_err()));
+}
+
+#[test]
+fn list_error_test_2(){
+    assert_eq!(("Index is out of range".to_string(), 6), located(calc::eval("[1, 2][2]".to_string()).unwrap_err()));
+}
+
+#[test]
+fn list_error_test_3(){
+    assert_eq!(("): Parenthesis does not close the [ at 0".to_string(), 5), located(calc::eval("[1, 2)".to_string()).unwrap_err()));
+}
+
+#[test]
+fn list_error_test_4(){
+    assert_eq!(("Value is not a list".to_string(), 0), located(calc::eval("len(3)".to_string()).unwrap_err()));
+}
+
+#[test]
+fn matrix_test_1(){
+    assert_eq!("[[19, 22], [43, 50]]", calc::eval("[[1, 2], [3, 4]] @ [[5, 6], [7, 8]]".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn matrix_test_2(){
+    assert_eq!("[5, 11]", calc::eval("[[1, 2], [3, 4]] @ [1, 2]".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn matrix_test_3(){
+    assert_eq!("32", calc::eval("[1, 2, 3] @ [4, 5, 6]".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn matrix_test_4(){
+    assert_eq!("[[1, 4], [2, 5], [3, 6]]", calc::eval("transpose([[1, 2, 3], [4, 5, 6]])".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn matrix_test_5(){
+    assert_eq!("-2", calc::eval_with("det([[1, 2], [3, 4]])".to_string(), &signed()).unwrap().to_string());
+}
+
+#[test]
+fn matrix_test_6(){
+    assert_eq!("-306", calc::eval_with("det([[6, 1, 1], [4, -2, 5], [2, 8, 7]])".to_string(), &big()).unwrap().to_string());
+}
+
+#[test]
+fn matrix_test_7(){
+    assert_eq!("[[-2, 1], [3/2, -1/2]]", calc::eval("inv([[1, 2], [3, 4]])".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn matrix_test_8(){
+    assert_eq!("[[1, 0], [0, 1]]", calc::eval("inv([[1, 2], [3, 4]]) @ [[1, 2], [3, 4]]".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn matrix_test_9(){
+    assert_eq!("[[0.5, 0], [0, 0.25]]", calc::eval_with("inv([[2, 0], [0, 4]])".to_string(), &float()).unwrap().to_string());
+}
+
+#[test]
+fn matrix_test_10(){
+    assert_eq!("[[1, 0, 0], [0, 1, 0], [0, 0, 1]]", calc::eval("identity(3)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn matrix_test_11(){
+    assert_eq!("[1, 3]", calc::eval("solve([[2, 1], [1, 3]], [5, 10])".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn matrix_test_12(){
+    assert_eq!("[[2, 4], [6, 8]]", calc::eval("[[1, 2], [3, 4]] * 2".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn matrix_test_13(){
+    assert_eq!("3", calc::eval("@9".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn matrix_error_test_1(){
+    assert_eq!(("Matrix dimensions do not fit".to_string(), 17), located(calc::eval("[[1, 2], [3, 4]] @ [1, 2, 3]".to_string()).unwrap_err()));
+}
+
+#[test]
+fn matrix_error_test_2(){
+    assert_eq!(("Matrix dimensions do not fit".to_string(), 0), located(calc::eval("det([[1, 2, 3], [4, 5, 6]])".to_string()).unwrap_err()));
+}
+
+#[test]
+fn matrix_error_test_3(){
+    assert_eq!(("Matrix is singular".to_string(), 0), located(calc::eval("inv([[1, 2], [2, 4]])".to_string()).unwrap_err()));
+}
+
+#[test]
+fn matrix_error_test_4(){
+    assert_eq!(("Matrix dimensions do not fit".to_string(), 0), located(calc::eval("transpose([[1, 2], [3]])".to_string()).unwrap_err()));
+}
+
+#[test]
+fn range_literal_test_1(){
+    assert_eq!("[1, 2, 3, 4, 5]", calc::eval("1..5".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn range_literal_test_2(){
+    assert_eq!("[0, 5, 10]", calc::eval("0..10 step 5".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn range_literal_test_3(){
+    assert_eq!("[0, 0.25, 0.5, 0.75, 1]", calc::eval_with("0..1 step 0.25".to_string(), &float()).unwrap().to_string());
+}
+
+#[test]
+fn range_literal_test_4(){
+    assert_eq!("[3, 1, -1]", calc::eval_with("3..-1 step -2".to_string(), &signed()).unwrap().to_string());
+}
+
+#[test]
+fn range_literal_test_5(){
+    assert_eq!("15", calc::eval("sum(1..5)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn reduce_test_1(){
+    assert_eq!("338350", calc::eval("sum(i, 1..100, i**2)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn reduce_test_2(){
+    assert_eq!("120", calc::eval("prod(k, 1..5, k)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn reduce_test_3(){
+    assert_eq!("2500", calc::eval("sum(n, 1..100 step 2, n)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn reduce_test_4(){
+    calc::eval("rdl = [1, 2, 3]".to_string()).unwrap();
+    assert_eq!("14", calc::eval("sum(x, rdl, x*x)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn reduce_test_5(){
+    calc::eval("rdf(n) = sum(i, 1..n, i)".to_string()).unwrap();
+    assert_eq!("5050", calc::eval("rdf(100)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn reduce_test_6(){
+    assert_eq!("0", calc::eval("sum(i, 5..1, i)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn reduce_test_7(){
+    assert_eq!("50005000", calc::eval("sum(j, 1..10000, j)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn reduce_test_8(){
+    assert_eq!("2", calc::eval_with("sum(i, 1..10, 1/2**i) + 1/2**10 + 1".to_string(), &rational()).unwrap().to_string());
+}
+
+#[test]
+fn reduce_test_9(){
+    assert_eq!("6", calc::eval("sum([1, 2, 3])".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn reduce_error_test_1(){
+    assert_eq!(("Wrong number of arguments".to_string(), 0), located(calc::eval("sum(i, 1..10)".to_string()).unwrap_err()));
+}
+
+#[test]
+fn reduce_error_test_2(){
+    assert_eq!(("Expected a variable name like `sum(i, 1..10, i**2)`".to_string(), 0), located(calc::eval("sum(2, 1..10, 3)".to_string()).unwrap_err()));
+}
+
+#[test]
+fn reduce_error_test_3(){
+    assert_eq!(("Argumentum is out of the domain".to_string(), 1), located(calc::eval("1..5 step 0".to_string()).unwrap_err()));
+}
+
+#[test]
+fn reduce_error_test_4(){
+    assert_eq!(("rbound: Variable is not defined, did you mean `round`?".to_string(), 15), located(calc::eval("sum(rbound, 1..rbound, rbound)".to_string()).unwrap_err()));
+}
+
+#[test]
+fn reduce_error_test_5(){
+    assert_eq!(("Argumentum is too large".to_string(), 8), located(calc::eval("sum(i, 1..10**9, i)".to_string()).unwrap_err()));
+    assert_eq!(("Argumentum is too large".to_string(), 9), located(calc::eval("prod(i, 1..10**9 step 2, i)".to_string()).unwrap_err()));
+}
+
+#[test]
+fn reduce_test_10(){
+    // A fractional step needs the float mode
+    assert_eq!("0.1: Token cannot be parsed", calc::eval("sum(x, 0..1 step 0.1, x**2)".to_string()).unwrap_err().to_string());
+    assert_eq!("3.85", calc::eval_with("round(sum(x, 0..1 step 0.1, x**2) * 100) / 100".to_string(), &float()).unwrap().to_string());
+}
+
+#[test]
+fn stats_test_1(){
+    assert_eq!("8", calc::eval("mean([3, 9, 12])".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn stats_test_2(){
+    assert_eq!("3/2", calc::eval("mean([1, 2])".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn stats_test_3(){
+    assert_eq!("5", calc::eval("median([9, 1, 5])".to_string()).unwrap().to_string());
+    assert_eq!("7/2", calc::eval("median([4, 1, 3, 9])".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn stats_test_4(){
+    assert_eq!("2", calc::eval("mode([3, 2, 5, 3, 2])".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn stats_test_5(){
+    assert_eq!("4", calc::eval("variance([2, 4, 4, 4, 5, 5, 7, 9])".to_string()).unwrap().to_string());
+    assert_eq!("2", calc::eval("stddev([2, 4, 4, 4, 5, 5, 7, 9])".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn stats_test_6(){
+    assert_eq!("32/7", calc::eval("variance_s([2, 4, 4, 4, 5, 5, 7, 9])".to_string()).unwrap().to_string());
+    assert_eq!("2.138089935299395", calc::eval("stddev_s([2, 4, 4, 4, 5, 5, 7, 9])".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn stats_test_7(){
+    assert_eq!("99.01", calc::eval_with("percentile(1..100, 99)".to_string(), &float()).unwrap().to_string());
+    assert_eq!("50", calc::eval("percentile([10, 20, 30, 40, 50], 100)".to_string()).unwrap().to_string());
+    assert_eq!("25", calc::eval("percentile([10, 20, 30, 40], 50)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn stats_test_8(){
+    assert_eq!("9", calc::eval("max([3, 9], 4)".to_string()).unwrap().to_string());
+    assert_eq!("1", calc::eval("min([3, 9, 1])".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn stats_test_9(){
+    assert_eq!("3", calc::eval("count([1, 2, 3])".to_string()).unwrap().to_string());
+    assert_eq!("2", calc::eval("count([1, 2, 1], 1)".to_string()).unwrap().to_string());
+    assert_eq!("2", calc::eval("count([1, 5, 9], x -> x > 3)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn stats_test_10(){
+    assert_eq!("[2, 1, 3]", calc::eval("histogram([1, 2, 5, 8, 9, 10], 3)".to_string()).unwrap().to_string());
+    assert_eq!("[3, 0]", calc::eval("histogram([4, 4, 4], 2)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn stats_test_11(){
+    assert_eq!("1", calc::eval("correlation([1, 2, 3], [2, 4, 6])".to_string()).unwrap().to_string());
+    assert_eq!("-1", calc::eval_with("correlation([1, 2, 3], [3, 2, 1])".to_string(), &signed()).unwrap().to_string());
+}
+
+#[test]
+fn stats_test_12(){
+    assert_eq!("2.5", calc::eval_with("mean([1.5, 2.5, 3.5])".to_string(), &float()).unwrap().to_string());
+}
+
+#[test]
+fn stats_error_test_1(){
+    assert_eq!(("Argumentum is out of the domain".to_string(), 0), located(calc::eval("mean([])".to_string()).unwrap_err()));
+}
+
+#[test]
+fn stats_error_test_2(){
+    assert_eq!(("Argumentum is out of the domain".to_string(), 0), located(calc::eval("percentile([1, 2], 101)".to_string()).unwrap_err()));
+}
+
+#[test]
+fn stats_error_test_3(){
+    assert_eq!(("Lists have different lengths".to_string(), 0), located(calc::eval("correlation([1, 2], [1, 2, 3])".to_string()).unwrap_err()));
+}
+
+#[test]
+fn stats_error_test_4(){
+    assert_eq!(("Argumentum is out of the domain".to_string(), 0), located(calc::eval("variance_s([5])".to_string()).unwrap_err()));
+}
+
+#[test]
+fn integer_test_1(){
+    assert_eq!("10", calc::eval("nCr(5, 2)".to_string()).unwrap().to_string());
+    assert_eq!("20", calc::eval("nPr(5, 2)".to_string()).unwrap().to_string());
+    assert_eq!("0", calc::eval("nCr(2, 5)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn integer_test_2(){
+    assert_eq!("100891344545564193334812497256", calc::eval_with("nCr(100, 50)".to_string(), &big()).unwrap().to_string());
+}
+
+#[test]
+fn integer_test_3(){
+    assert_eq!("1", calc::eval("is_prime(97)".to_string()).unwrap().to_string());
+    assert_eq!("0", calc::eval("is_prime(91)".to_string()).unwrap().to_string());
+    assert_eq!("1", calc::eval_with("is_prime(2**127 - 1)".to_string(), &big()).unwrap().to_string());
+}
+
+#[test]
+fn integer_test_4(){
+    assert_eq!("101", calc::eval("next_prime(97)".to_string()).unwrap().to_string());
+    assert_eq!("2", calc::eval("next_prime(0)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn integer_test_5(){
+    assert_eq!("[2, 2, 3]", calc::eval("factorize(12)".to_string()).unwrap().to_string());
+    assert_eq!("[]", calc::eval("factorize(1)".to_string()).unwrap().to_string());
+    assert_eq!("[1000003, 1000033]", calc::eval_with("factorize(1000003 * 1000033)".to_string(), &big()).unwrap().to_string());
+}
+
+#[test]
+fn integer_test_6(){
+    assert_eq!("40", calc::eval("totient(100)".to_string()).unwrap().to_string());
+    assert_eq!("1", calc::eval("totient(1)".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn integer_test_7(){
+    assert_eq!("4", calc::eval("modinv(3, 11)".to_string()).unwrap().to_string());
+    assert_eq!("2", calc::eval_with("modinv(-5, 11)".to_string(), &signed()).unwrap().to_string());
+}
+
+#[test]
+fn integer_test_8(){
+    assert_eq!("445", calc::eval("modpow(4, 13, 497)".to_string()).unwrap().to_string());
+    assert_eq!("1", calc::eval("modpow(2, 4000000000, 3)".to_string()).unwrap().to_string());
+    assert_eq!("4", calc::eval_with("modpow(3, -1, 11)".to_string(), &signed()).unwrap().to_string());
+}
+
+#[test]
+fn integer_test_9(){
+    assert_eq!("55", calc::eval("fib(10)".to_string()).unwrap().to_string());
+    assert_eq!("354224848179261915075", calc::eval_with("fib(100)".to_string(), &big()).unwrap().to_string());
+    // The definition of the recursion example replaces the built-in one, it is in the same test so the built-in is checked first
+    calc::eval("fib(n) = n < 2 ? n : fib(n-1) + fib(n-2)".to_string()).unwrap();
+    assert_eq!("55", calc::eval("fib(10)".to_string()).unwrap().to_string());
+    let settings = calc::Settings { max_depth: 5, ..Default::default() };
+    assert_eq!("Maximum recursion depth exceeded", calc::eval_with("fib(10)".to_string(), &settings).unwrap_err().to_string());
+    // The arity of the built-in does not apply to the calls of the definition
+    assert_eq!(Vec::<CalcError>::new(), calc::check("gcd(n) = n == 0 ? 0 : gcd(n - 1)"));
+}
+
+#[test]
+fn integer_test_10(){
+    assert_eq!("3", calc::eval("isqrt(15)".to_string()).unwrap().to_string());
+    assert_eq!("1000000000000", calc::eval_with("isqrt(10**24 + 1)".to_string(), &big()).unwrap().to_string());
+    assert_eq!("4", calc::eval_with("isqrt(17.5 - 0.5)".to_string(), &float()).unwrap().to_string());
+}
+
+#[test]
+fn integer_test_11(){
+    assert_eq!("4", calc::eval("iroot(80, 3)".to_string()).unwrap().to_string());
+    assert_eq!("-3", calc::eval_with("iroot(-27, 3)".to_string(), &signed()).unwrap().to_string());
+}
+
+#[test]
+fn integer_error_test_1(){
+    assert_eq!(("Argumentum is out of the domain".to_string(), 0), located(calc::eval("modinv(4, 8)".to_string()).unwrap_err()));
+}
+
+#[test]
+fn integer_error_test_2(){
+    assert_eq!(("Argumentum cannot be negative".to_string(), 0), located(calc::eval_with("iroot(-16, 2)".to_string(), &signed()).unwrap_err()));
+}
+
+#[test]
+fn integer_error_test_3(){
+    assert_eq!(("Divided by zero".to_string(), 0), located(calc::eval("modpow(2, 3, 0)".to_string()).unwrap_err()));
+}
+
+#[test]
+fn integer_error_test_4(){
+    assert_eq!(("Argumentum is out of the domain".to_string(), 0), located(calc::eval("factorize(0)".to_string()).unwrap_err()));
+}
+
+fn degrees() -> calc::Settings {
+    calc::Settings { angle: AngleMode::Deg, ..Default::default() }
+}
+
+#[test]
+fn trig_test_1(){
+    assert_eq!("0.5", calc::eval_with("sin(30)".to_string(), &degrees()).unwrap().to_string());
+    assert_eq!("0", calc::eval_with("cos(90)".to_string(), &degrees()).unwrap().to_string());
+    assert_eq!("1", calc::eval_with("tan(45)".to_string(), &degrees()).unwrap().to_string());
+}
+
+#[test]
+fn trig_test_2(){
+    assert_eq!("0.8414709848078965", calc::eval_with("sin(1)".to_string(), &float()).unwrap().to_string());
+    assert_eq!("1", calc::eval_with("cos(0)".to_string(), &float()).unwrap().to_string());
+}
+
+#[test]
+fn trig_test_3(){
+    assert_eq!("30", calc::eval_with("asin(0.5)".to_string(), &calc::Settings { mode: NumberMode::Float, angle: AngleMode::Deg, ..Default::default() }).unwrap().to_string());
+    assert_eq!("45", calc::eval_with("atan2(1, 1)".to_string(), &degrees()).unwrap().to_string());
+    assert_eq!("50", calc::eval_with("atan(1)".to_string(), &calc::Settings { angle: AngleMode::Grad, ..Default::default() }).unwrap().to_string());
+}
+
+#[test]
+fn trig_test_4(){
+    assert_eq!("1", calc::eval_with("2 * sin(30)".to_string(), &degrees()).unwrap().to_string());
+    assert_eq!("1/2", calc::eval_with("sin(30)".to_string(), &calc::Settings { mode: NumberMode::Rational, angle: AngleMode::Deg, ..Default::default() }).unwrap().to_string());
+}
+
+#[test]
+fn trig_test_5(){
+    assert_eq!("5", calc::eval("hypot(3, 4)".to_string()).unwrap().to_string());
+    assert_eq!("0", calc::eval("sinh(0)".to_string()).unwrap().to_string());
+    assert_eq!("1.1752011936438014", calc::eval_with("sinh(1)".to_string(), &float()).unwrap().to_string());
+}
+
+#[test]
+fn trig_test_6(){
+    calc::eval_with("trig_a = sin(30)".to_string(), &degrees()).unwrap();
+    assert_eq!("1", calc::eval_with("trig_a * 2".to_string(), &degrees()).unwrap().to_string());
+}
+
+#[test]
+fn trig_test_7(){
+    assert_eq!("1.5707963267948966-1.3169578969248166i", calc::eval_with("asin(2)".to_string(), &complex()).unwrap().to_string());
+}
+
+#[test]
+fn trig_error_test_1(){
+    assert_eq!(("Argumentum is out of the domain".to_string(), 0), located(calc::eval_with("asin(2)".to_string(), &float()).unwrap_err()));
+}
+
+#[test]
+fn trig_error_test_2(){
+    assert_eq!(("Argumentum is out of the domain".to_string(), 0), located(calc::eval_with("tan(270)".to_string(), &degrees()).unwrap_err()));
+}
+
+#[test]
+fn angle_setting_test_1(){
+    assert!(calc::set_option("angle", "turns").is_err());
+}
+
+#[test]
+fn constant_test_1(){
+    assert_eq!("3.141592653589793", calc::eval("pi".to_string()).unwrap().to_string());
+    assert_eq!("6.283185307179586", calc::eval("2 * pi".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn constant_test_2(){
+    assert_eq!("2.718281828459045", calc::eval_with("e".to_string(), &float()).unwrap().to_string());
+    assert_eq!("1", calc::eval_with("tau / pi - 1".to_string(), &float()).unwrap().to_string());
+    assert_eq!("1", calc::eval_with("phi**2 - phi".to_string(), &float()).unwrap().to_string());
+}
+
+#[test]
+fn constant_test_3(){
+    assert_eq!("inf", calc::eval_with("inf".to_string(), &float()).unwrap().to_string());
+    assert_eq!("NaN", calc::eval_with("nan".to_string(), &float()).unwrap().to_string());
+    assert_eq!("1", calc::eval_with("-inf < 0".to_string(), &float()).unwrap().to_string());
+}
+
+#[test]
+fn constant_test_4(){
+    assert_eq!("299792458", calc::eval("const.c".to_string()).unwrap().to_string());
+    assert_eq!("9.80665", calc::eval_with("const.g".to_string(), &float()).unwrap().to_string());
+    assert_eq!("6.02214076e23", calc::eval_with("const.NA".to_string(), &float()).unwrap().to_string());
+}
+
+#[test]
+fn constant_test_5(){
+    assert_eq!("0", calc::eval_with("sin(pi)".to_string(), &float()).unwrap().to_string());
+    assert_eq!("3141592653589793/1000000000000000", calc::eval_with("pi".to_string(), &rational()).unwrap().to_string());
+}
+
+#[test]
+fn constant_test_6(){
+    calc::eval("cshadow(e) = e * 2".to_string()).unwrap();
+    assert_eq!("6", calc::eval("cshadow(3)".to_string()).unwrap().to_string());
+    assert_eq!("[2, 4]", calc::eval("map(pi -> pi * 2, [1, 2])".to_string()).unwrap().to_string());
+}
+
+#[test]
+fn constant_error_test_1(){
+    assert_eq!(("pi: Name is a built-in constant, only a parameter like `f(e) = e*2` can shadow it".to_string(), 0), located(calc::eval("pi = 3".to_string()).unwrap_err()));
+}
+
+#[test]
+fn constant_error_test_2(){
+    // Only the part after `const.` is compared
+    assert_eq!(("const.x: Variable is not defined".to_string(), 0), located(calc::eval("const.x + 1".to_string()).unwrap_err()));
+    assert_eq!(("const.xyz: Variable is not defined".to_string(), 0), located(calc::eval("const.xyz + 1".to_string()).unwrap_err()));
+    assert_eq!(("const.hbr: Variable is not defined, did you mean `const.hbar`?".to_string(), 0), located(calc::eval("const.hbr + 1".to_string()).unwrap_err()));
+}
+
@@ -0,0 +1,103 @@
+use num_complex::Complex64;
+
+use crate::error::{CalcError, Span};
+use crate::number::{AngleMode, CalcNumber, NumberMode};
+
+fn to_float(num: &CalcNumber) -> f64 {
+    return match num.convert(NumberMode::Float) {
+        CalcNumber::Float(v) => v,
+        _ => unreachable!()
+    };
+}
+
+// Removes the rounding error of results like `sin(30)` which would be 0.49999999999999994
+// a small result is only rounded to zero when the argument is not small itself, like `sin(pi)`
+fn snap(v: f64, arg: f64) -> f64 {
+    let half = (v * 2.0).round() / 2.0;
+    if half == 0.0 {
+        return if v.abs() < 1e-15 && arg.abs() >= 1.0 { 0.0 } else { v };
+    }
+    return if (v - half).abs() < half.abs() * 1e-14 { half } else { v };
+}
+
+pub fn call(name: &str, args: &[CalcNumber], angle: AngleMode, char_idx: usize) -> Result<CalcNumber, CalcError> {
+    let imaginary = |arg: &CalcNumber| matches!(arg, CalcNumber::Complex(v) if v.im != 0.0);
+    // Only the real numbers have an angle and a length like this
+    if matches!(name, "atan2" | "hypot") && args.iter().any(imaginary) { return Err(CalcError::Unsupported(Span::at(char_idx))); }
+    let res = real(name, args, angle, char_idx);
+    // The complex mode gives a complex result where the real functions are not defined, like `asin(2)`
+    if let CalcNumber::Complex(v) = &args[0] && (v.im != 0.0 || res.is_err()) && !matches!(name, "atan2" | "hypot") {
+        return Ok(CalcNumber::Complex(complex(name, *v, angle)));
+    }
+    // The integer modes give a float since `sin(30)` is rarely whole
+    let mode = if args[0].mode().is_whole() { NumberMode::Float } else { args[0].mode() };
+    return Ok(CalcNumber::Float(res?).convert(mode));
+}
+
+fn real(name: &str, args: &[CalcNumber], angle: AngleMode, char_idx: usize) -> Result<f64, CalcError> {
+    // Radians in one unit of the angle mode
+    let unit = std::f64::consts::TAU / angle.turn();
+    let x = to_float(&args[0]);
+    let res = match name {
+        "sin" | "cos" | "tan" => {
+            // A tangent of a right angle has no value, it is only exact in degrees and grads
+            let quarter = angle.turn() / 4.0;
+            if name == "tan" && angle != AngleMode::Rad && x % quarter == 0.0 && (x / quarter) % 2.0 != 0.0 {
+                return Err(CalcError::Domain(Span::at(char_idx)));
+            }
+            // Whole turns are removed first, so `sin(36000)` is as exact as `sin(0)`
+            let rad = (x % angle.turn()) * unit;
+            let res = match name {
+                "sin" => rad.sin(),
+                "cos" => rad.cos(),
+                _ => rad.tan()
+            };
+            snap(res, x)
+        },
+        "asin" | "acos" | "atan" => {
+            let res = match name {
+                "asin" => x.asin(),
+                "acos" => x.acos(),
+                _ => x.atan()
+            };
+            snap(res / unit, res)
+        },
+        "atan2" => {
+            let res = x.atan2(to_float(&args[1]));
+            snap(res / unit, res)
+        },
+        "sinh" => x.sinh(),
+        "cosh" => x.cosh(),
+        "tanh" => x.tanh(),
+        "asinh" => x.asinh(),
+        "acosh" => x.acosh(),
+        "atanh" => {
+            if x.abs() >= 1.0 { return Err(CalcError::Domain(Span::at(char_idx))); }
+            x.atanh()
+        },
+        "hypot" => args.iter().fold(0.0, |acc: f64, arg| acc.hypot(to_float(arg))),
+        _ => unreachable!()
+    };
+    if res.is_nan() { return Err(CalcError::Domain(Span::at(char_idx))); }
+    return Ok(res);
+}
+
+// The angle mode scales the complex arguments like the real ones
+fn complex(name: &str, v: Complex64, angle: AngleMode) -> Complex64 {
+    let unit = std::f64::consts::TAU / angle.turn();
+    return match name {
+        "sin" => (v * unit).sin(),
+        "cos" => (v * unit).cos(),
+        "tan" => (v * unit).tan(),
+        "asin" => v.asin() / unit,
+        "acos" => v.acos() / unit,
+        "atan" => v.atan() / unit,
+        "sinh" => v.sinh(),
+        "cosh" => v.cosh(),
+        "tanh" => v.tanh(),
+        "asinh" => v.asinh(),
+        "acosh" => v.acosh(),
+        "atanh" => v.atanh(),
+        _ => unreachable!()
+    };
+}